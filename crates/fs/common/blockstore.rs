@@ -32,6 +32,32 @@ pub trait BlockStore {
     async fn get_block<'a>(&'a self, cid: &Cid) -> Result<Cow<'a, Vec<u8>>>;
     async fn put_block(&mut self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid>;
 
+    /// Retrieves many blocks, in the order of their CIDs.
+    ///
+    /// The default implementation gets one block after the other. Stores with a round-trip per
+    /// call, like remote stores, should override it to get all of the blocks at once.
+    async fn get_blocks<'a>(&'a self, cids: &[Cid]) -> Result<Vec<Cow<'a, Vec<u8>>>> {
+        let mut blocks = Vec::with_capacity(cids.len());
+        for cid in cids {
+            blocks.push(self.get_block(cid).await?);
+        }
+
+        Ok(blocks)
+    }
+
+    /// Stores many arrays of bytes, returning their CIDs in order.
+    ///
+    /// The default implementation puts one block after the other. Stores with a round-trip per
+    /// call, like remote stores, should override it to put all of the blocks at once.
+    async fn put_blocks(&mut self, blocks: Vec<(Vec<u8>, IpldCodec)>) -> Result<Vec<Cid>> {
+        let mut cids = Vec::with_capacity(blocks.len());
+        for (bytes, codec) in blocks {
+            cids.push(self.put_block(bytes, codec).await?);
+        }
+
+        Ok(cids)
+    }
+
     async fn put_serializable<V: Serialize>(&mut self, value: &V) -> Result<Cid> {
         let ipld = ipld_serde::to_ipld(value)?;
 
@@ -85,6 +111,17 @@ pub trait BlockStore {
     }
 }
 
+/// For block stores that can enumerate and delete the blocks they hold, so blocks that are no
+/// longer reachable can be removed with [`gc`](crate::gc).
+#[async_trait(?Send)]
+pub trait PrunableBlockStore: BlockStore {
+    /// Lists the CIDs of all blocks in the store.
+    async fn list_blocks(&self) -> Result<Vec<Cid>>;
+
+    /// Deletes the block with the given CID. Deleting a block that is not stored is not an error.
+    async fn delete_block(&mut self, cid: &Cid) -> Result<()>;
+}
+
 /// An in-memory block store to simulate IPFS.
 ///
 /// IPFS is basically a glorified HashMap.
@@ -125,6 +162,23 @@ impl BlockStore for MemoryBlockStore {
     }
 }
 
+#[async_trait(?Send)]
+impl PrunableBlockStore for MemoryBlockStore {
+    /// Lists the CIDs of all blocks in the block store.
+    async fn list_blocks(&self) -> Result<Vec<Cid>> {
+        self.0
+            .keys()
+            .map(|cid| Ok(Cid::try_from(cid.as_str())?))
+            .collect()
+    }
+
+    /// Deletes the block with given CID from the block store.
+    async fn delete_block(&mut self, cid: &Cid) -> Result<()> {
+        self.0.remove(&cid.to_string());
+        Ok(())
+    }
+}
+
 //--------------------------------------------------------------------------------------------------
 // Functions
 //--------------------------------------------------------------------------------------------------
@@ -0,0 +1,481 @@
+//! Import and export of filesystems as [CAR](https://ipld.io/specs/transport/car/) archives.
+//!
+//! An archive holds every block reachable from a root, e.g. the CID of a stored
+//! [`PublicDirectory`](crate::PublicDirectory) or [`PrivateForest`](crate::private::PrivateForest),
+//! so a filesystem can be moved between block stores without a network.
+
+use std::{collections::HashSet, io::Cursor};
+
+use anyhow::{bail, ensure, Result};
+use futures::{io, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use libipld::{
+    cbor::DagCborCodec,
+    cid::Version,
+    codec::{Decode, Encode},
+    serde as ipld_serde, Cid, Ipld, IpldCodec,
+};
+use multihash::{Code, MultihashDigest};
+use serde::{Deserialize, Serialize};
+
+use super::{walk, BlockStore, FsError};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The fixed bytes a CARv2 archive starts with. They decode as a CARv1 header of version 2.
+const CAR_V2_PRAGMA: [u8; 11] = [
+    0x0a, 0xa1, 0x67, 0x76, 0x65, 0x72, 0x73, 0x69, 0x6f, 0x6e, 0x02,
+];
+
+/// The number of bytes in the CARv2 header that follows the pragma.
+const CAR_V2_HEADER_SIZE: usize = 40;
+
+/// The maximum number of bytes in an unsigned varint.
+const MAX_VARINT_SIZE: usize = 9;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+#[derive(Serialize, Deserialize)]
+struct CarHeader {
+    version: u64,
+    #[serde(default)]
+    roots: Vec<Cid>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Writes a CARv1 archive of every block reachable from the root to the writer.
+///
+/// Links are followed through DAG-CBOR blocks, like public nodes and private forest nodes, and
+/// through DAG-PB blocks of UnixFS files. Blocks are written in depth-first order, starting with
+/// the root, and each block is written once.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Rc;
+///
+/// use chrono::Utc;
+/// use wnfs::{car, MemoryBlockStore, PublicDirectory, PublicOpResult};
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &mut MemoryBlockStore::default();
+///     let PublicOpResult { root_dir, .. } = Rc::new(PublicDirectory::new(Utc::now()))
+///         .mkdir(&["pictures".into(), "cats".into()], Utc::now(), store)
+///         .await
+///         .unwrap();
+///     let root_cid = root_dir.store(store).await.unwrap();
+///
+///     let mut archive = Vec::new();
+///     car::export(&root_cid, store, &mut archive).await.unwrap();
+///
+///     // On another machine.
+///     let store = &mut MemoryBlockStore::default();
+///     let roots = car::import(&mut archive.as_slice(), store).await.unwrap();
+///
+///     assert_eq!(roots, vec![root_cid]);
+/// }
+/// ```
+pub async fn export<B, W>(root_cid: &Cid, store: &B, writer: &mut W) -> Result<()>
+where
+    B: BlockStore,
+    W: AsyncWrite + Unpin,
+{
+    write_header(
+        &CarHeader {
+            version: 1,
+            roots: vec![*root_cid],
+        },
+        writer,
+    )
+    .await?;
+
+    let mut visited = HashSet::new();
+    let mut stack = vec![*root_cid];
+    while let Some(cid) = stack.pop() {
+        if !visited.insert(cid) {
+            continue;
+        }
+
+        let bytes = store.get_block(&cid).await?;
+        write_section(&cid, &bytes, writer).await?;
+
+        // Push the links in reverse, so they are written in the order they appear in the block.
+        stack.extend(walk::block_links(&cid, &bytes)?.into_iter().rev());
+    }
+
+    writer.flush().await?;
+
+    Ok(())
+}
+
+/// Writes a CARv2 archive of every block reachable from the root to the writer.
+///
+/// The archive wraps the same data [`export`] writes. No index is written, as [`import`] reads
+/// every block anyway.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Rc;
+///
+/// use wnfs::{car, private::PrivateForest, BlockStore, MemoryBlockStore};
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &mut MemoryBlockStore::default();
+///     let forest = Rc::new(PrivateForest::new());
+///     let root_cid = store.put_async_serializable(&forest).await.unwrap();
+///
+///     let mut archive = Vec::new();
+///     car::export_v2(&root_cid, store, &mut archive).await.unwrap();
+///
+///     let store = &mut MemoryBlockStore::default();
+///     let roots = car::import(&mut archive.as_slice(), store).await.unwrap();
+///
+///     assert_eq!(roots, vec![root_cid]);
+/// }
+/// ```
+pub async fn export_v2<B, W>(root_cid: &Cid, store: &B, writer: &mut W) -> Result<()>
+where
+    B: BlockStore,
+    W: AsyncWrite + Unpin,
+{
+    // The header holds the size of the data, so the data is collected first.
+    let mut data = Vec::new();
+    export(root_cid, store, &mut data).await?;
+
+    let data_offset = (CAR_V2_PRAGMA.len() + CAR_V2_HEADER_SIZE) as u64;
+    let mut header = [0; CAR_V2_HEADER_SIZE];
+    header[16..24].copy_from_slice(&data_offset.to_le_bytes());
+    header[24..32].copy_from_slice(&(data.len() as u64).to_le_bytes());
+
+    writer.write_all(&CAR_V2_PRAGMA).await?;
+    writer.write_all(&header).await?;
+    writer.write_all(&data).await?;
+    writer.flush().await?;
+
+    Ok(())
+}
+
+/// Reads a CARv1 or CARv2 archive from the reader, stores its blocks in the block store and
+/// returns the roots of the archive.
+///
+/// Every block is checked against its CID before it is stored.
+///
+/// # Examples
+///
+/// ```
+/// use libipld::IpldCodec;
+/// use wnfs::{car, BlockStore, MemoryBlockStore};
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &mut MemoryBlockStore::default();
+///     let cid = store.put_block(b"hello world".to_vec(), IpldCodec::Raw).await.unwrap();
+///
+///     let mut archive = Vec::new();
+///     car::export(&cid, store, &mut archive).await.unwrap();
+///
+///     let other_store = &mut MemoryBlockStore::default();
+///     car::import(&mut archive.as_slice(), other_store).await.unwrap();
+///
+///     assert_eq!(other_store.get_block(&cid).await.unwrap().as_ref(), b"hello world");
+/// }
+/// ```
+pub async fn import<B, R>(reader: &mut R, store: &mut B) -> Result<Vec<Cid>>
+where
+    B: BlockStore,
+    R: AsyncRead + Unpin,
+{
+    let header = read_header(reader).await?;
+    match header.version {
+        1 => {
+            read_sections(reader, store).await?;
+            Ok(header.roots)
+        }
+        2 => {
+            let mut v2_header = [0; CAR_V2_HEADER_SIZE];
+            reader.read_exact(&mut v2_header).await?;
+
+            let data_offset = u64::from_le_bytes(v2_header[16..24].try_into()?);
+            let data_size = u64::from_le_bytes(v2_header[24..32].try_into()?);
+            let header_end = (CAR_V2_PRAGMA.len() + CAR_V2_HEADER_SIZE) as u64;
+            ensure!(
+                data_offset >= header_end,
+                FsError::InvalidCarFile(format!("Data offset {data_offset} overlaps the header"))
+            );
+
+            // Skip any padding before the data.
+            io::copy(reader.take(data_offset - header_end), &mut io::sink()).await?;
+
+            let data = &mut reader.take(data_size);
+            let header = read_header(data).await?;
+            ensure!(
+                header.version == 1,
+                FsError::InvalidCarFile(format!(
+                    "Expected CARv1 data, got version {}",
+                    header.version
+                ))
+            );
+
+            read_sections(data, store).await?;
+            Ok(header.roots)
+        }
+        version => bail!(FsError::InvalidCarFile(format!(
+            "Unsupported version {version}"
+        ))),
+    }
+}
+
+/// Writes a length-prefixed, DAG-CBOR encoded CARv1 header.
+async fn write_header<W: AsyncWrite + Unpin>(header: &CarHeader, writer: &mut W) -> Result<()> {
+    let mut bytes = Vec::new();
+    ipld_serde::to_ipld(header)?.encode(DagCborCodec, &mut bytes)?;
+
+    writer.write_all(&encode_varint(bytes.len() as u64)).await?;
+    writer.write_all(&bytes).await?;
+
+    Ok(())
+}
+
+/// Writes a block as a section: its length-prefixed CID and bytes.
+async fn write_section<W: AsyncWrite + Unpin>(
+    cid: &Cid,
+    bytes: &[u8],
+    writer: &mut W,
+) -> Result<()> {
+    let cid_bytes = cid.to_bytes();
+
+    writer
+        .write_all(&encode_varint((cid_bytes.len() + bytes.len()) as u64))
+        .await?;
+    writer.write_all(&cid_bytes).await?;
+    writer.write_all(bytes).await?;
+
+    Ok(())
+}
+
+/// Reads a length-prefixed, DAG-CBOR encoded CARv1 header.
+async fn read_header<R: AsyncRead + Unpin>(reader: &mut R) -> Result<CarHeader> {
+    let bytes = match read_varint(reader).await? {
+        Some(size) => read_exact_size(reader, size).await?,
+        None => bail!(FsError::InvalidCarFile("Missing header".into())),
+    };
+
+    let ipld = Ipld::decode(DagCborCodec, &mut Cursor::new(bytes))?;
+    Ok(ipld_serde::from_ipld(ipld)?)
+}
+
+/// Reads sections until the end of the reader and stores their blocks.
+async fn read_sections<B, R>(reader: &mut R, store: &mut B) -> Result<()>
+where
+    B: BlockStore,
+    R: AsyncRead + Unpin,
+{
+    while let Some(size) = read_varint(reader).await? {
+        let section = read_exact_size(reader, size).await?;
+        let mut cursor = Cursor::new(section.as_slice());
+        let cid = Cid::read_bytes(&mut cursor)?;
+        let bytes = &section[cursor.position() as usize..];
+
+        // Block stores name blocks with CIDv1 and SHA-256, so other CIDs can't be stored as is.
+        ensure!(
+            cid.version() == Version::V1 && cid.hash().code() == u64::from(Code::Sha2_256),
+            FsError::InvalidCarFile(format!("Unsupported CID {cid}"))
+        );
+
+        if Code::Sha2_256.digest(bytes) != *cid.hash() {
+            bail!(FsError::BlockContentMismatch(cid));
+        }
+
+        let codec =
+            IpldCodec::try_from(cid.codec()).map_err(|_| FsError::UnsupportedCodec(cid.codec()))?;
+        store.put_block(bytes.to_vec(), codec).await?;
+    }
+
+    Ok(())
+}
+
+/// Reads exactly the given number of bytes, without trusting the size for the allocation.
+async fn read_exact_size<R: AsyncRead + Unpin>(reader: &mut R, size: u64) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.take(size).read_to_end(&mut bytes).await?;
+    ensure!(
+        bytes.len() as u64 == size,
+        FsError::InvalidCarFile("Unexpected end of file".into())
+    );
+
+    Ok(bytes)
+}
+
+/// Reads an unsigned varint, or nothing if the reader is at its end.
+async fn read_varint<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<u64>> {
+    let mut value = 0;
+    for i in 0..MAX_VARINT_SIZE {
+        let mut byte = [0];
+        if reader.read(&mut byte).await? == 0 {
+            if i == 0 {
+                return Ok(None);
+            }
+            bail!(FsError::InvalidCarFile("Unexpected end of file".into()));
+        }
+
+        value |= u64::from(byte[0] & 0x7f) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+
+    bail!(FsError::InvalidCarFile("Varint is too long".into()))
+}
+
+/// Encodes a number as an unsigned varint.
+fn encode_varint(mut value: u64) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(MAX_VARINT_SIZE);
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+
+    bytes
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod car_tests {
+    use std::rc::Rc;
+
+    use chrono::Utc;
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use test_log::test;
+
+    use super::*;
+    use crate::{
+        private::PrivateForest, public::unixfs::Chunker, MemoryBlockStore, Namefilter,
+        PrivateDirectory, PrivateOpResult, PublicDirectory, PublicOpResult,
+    };
+
+    #[test(async_std::test)]
+    async fn public_directory_can_be_moved_to_another_store() {
+        let store = &mut MemoryBlockStore::default();
+        let content = (0..10_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+
+        let PublicOpResult { root_dir, .. } = Rc::new(PublicDirectory::new(Utc::now()))
+            .write_content(
+                &["docs".into(), "notes.txt".into()],
+                content.clone(),
+                &Chunker::FixedSize { size: 1024 },
+                Utc::now(),
+                store,
+            )
+            .await
+            .unwrap();
+        let root_cid = root_dir.store(store).await.unwrap();
+
+        for v2 in [false, true] {
+            let mut archive = Vec::new();
+            if v2 {
+                export_v2(&root_cid, store, &mut archive).await.unwrap();
+            } else {
+                export(&root_cid, store, &mut archive).await.unwrap();
+            }
+
+            let other_store = &mut MemoryBlockStore::default();
+            let roots = import(&mut archive.as_slice(), other_store).await.unwrap();
+            assert_eq!(roots, vec![root_cid]);
+
+            let root_dir: PublicDirectory =
+                other_store.get_deserializable(&root_cid).await.unwrap();
+            let PublicOpResult { result, .. } = Rc::new(root_dir)
+                .read_content(&["docs".into(), "notes.txt".into()], other_store)
+                .await
+                .unwrap();
+
+            assert_eq!(result, content);
+        }
+    }
+
+    #[test(async_std::test)]
+    async fn private_forest_can_be_moved_to_another_store() {
+        let store = &mut MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        let PrivateOpResult { root_dir, hamt, .. } = root_dir
+            .write(
+                &["text.txt".into()],
+                true,
+                Utc::now(),
+                b"hello world".to_vec(),
+                Rc::new(PrivateForest::new()),
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+        let forest_cid = store.put_async_serializable(&hamt).await.unwrap();
+
+        let mut archive = Vec::new();
+        export(&forest_cid, store, &mut archive).await.unwrap();
+
+        let other_store = &mut MemoryBlockStore::default();
+        import(&mut archive.as_slice(), other_store).await.unwrap();
+
+        let hamt: PrivateForest = other_store.get_deserializable(&forest_cid).await.unwrap();
+        let PrivateOpResult { result, .. } = root_dir
+            .read(&["text.txt".into()], true, Rc::new(hamt), other_store)
+            .await
+            .unwrap();
+
+        assert_eq!(result, b"hello world");
+    }
+
+    #[test(async_std::test)]
+    async fn corrupted_blocks_are_rejected() {
+        let store = &mut MemoryBlockStore::default();
+        let cid = store
+            .put_block(b"hello world".to_vec(), IpldCodec::Raw)
+            .await
+            .unwrap();
+
+        let mut archive = Vec::new();
+        export(&cid, store, &mut archive).await.unwrap();
+        *archive.last_mut().unwrap() ^= 1;
+
+        let error = import(&mut archive.as_slice(), &mut MemoryBlockStore::default())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<FsError>(),
+            Some(FsError::BlockContentMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn varints_roundtrip() {
+        for value in [0, 1, 127, 128, 300, 16_384, u32::MAX as u64, (1 << 63) - 1] {
+            let bytes = encode_varint(value);
+            let decoded = async_std::task::block_on(read_varint(&mut bytes.as_slice()))
+                .unwrap()
+                .unwrap();
+
+            assert_eq!(decoded, value);
+        }
+    }
+}
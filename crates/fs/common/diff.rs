@@ -0,0 +1,33 @@
+//! File system diffs.
+
+use crate::NodeType;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The kind of change to a key or node between two versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeType {
+    /// It is only in the main version.
+    Add,
+    /// It is only in the other version.
+    Remove,
+    /// It is in both versions but differs.
+    Modify,
+}
+
+/// A file or directory that differs between two versions of a directory.
+///
+/// Changes are relative to the other version, so a node only in the main version is added. A
+/// directory that changed is reported as modified along with the changes to its entries. A node
+/// that changed from a file to a directory, or the other way round, is reported as removed and
+/// added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeChange {
+    pub r#type: ChangeType,
+    /// The path of the node, relative to the compared directories.
+    pub path: Vec<String>,
+    /// The type of the node. For removed nodes, this is its type in the other version.
+    pub node_type: NodeType,
+}
@@ -0,0 +1,318 @@
+//! A block store that keeps blocks in files on disk.
+
+use std::{
+    borrow::Cow,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use anyhow::{bail, Result};
+use async_std::fs;
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use libipld::{cid::Version, Cid, IpldCodec};
+use multihash::{Code, MultihashDigest};
+
+use super::{BlockStore, FsError, PrunableBlockStore};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The number of hex characters of the hash digest that name the shard directory of a block.
+const SHARD_PREFIX_SIZE: usize = 2;
+
+/// Counts temporary files, so concurrent writes in the same process never share one.
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A block store that keeps each block in its own file under a root directory, so blocks survive
+/// process restarts.
+///
+/// Blocks are sharded into subdirectories named after the first hex characters of their hash
+/// digest. Writes go to a temporary file that is then renamed into place, so a block file is
+/// either complete or missing. On read, the content of a block is checked against its CID.
+///
+/// # Examples
+///
+/// ```
+/// use libipld::IpldCodec;
+/// use wnfs::{BlockStore, DiskBlockStore};
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let dir = tempfile::tempdir().unwrap();
+///     let store = &mut DiskBlockStore::new(dir.path()).await.unwrap();
+///
+///     let cid = store.put_block(b"hello world".to_vec(), IpldCodec::Raw).await.unwrap();
+///
+///     // A new store on the same directory sees the same blocks.
+///     let store = DiskBlockStore::new(dir.path()).await.unwrap();
+///     let bytes = store.get_block(&cid).await.unwrap();
+///
+///     assert_eq!(bytes.as_ref(), b"hello world");
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DiskBlockStore {
+    root: PathBuf,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl DiskBlockStore {
+    /// Opens a block store in the given directory, creating the directory if it does not exist.
+    pub async fn new(root: impl AsRef<Path>) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root).await?;
+
+        Ok(Self { root })
+    }
+
+    /// Gets the directory the blocks are stored in.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Gets the path of the file a block is stored in.
+    fn block_path(&self, cid: &Cid) -> PathBuf {
+        let shard = cid
+            .hash()
+            .digest()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+
+        self.root
+            .join(&shard[..SHARD_PREFIX_SIZE.min(shard.len())])
+            .join(cid.to_string())
+    }
+}
+
+#[async_trait(?Send)]
+impl BlockStore for DiskBlockStore {
+    /// Stores an array of bytes in a file named after its CID.
+    async fn put_block(&mut self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+        let hash = Code::Sha2_256.digest(&bytes);
+        let cid = Cid::new(Version::V1, codec.into(), hash)?;
+
+        let path = self.block_path(&cid);
+        if fs::metadata(&path).await.is_ok() {
+            return Ok(cid);
+        }
+
+        let shard_dir = path.parent().unwrap();
+        let is_new_shard = fs::metadata(shard_dir).await.is_err();
+        fs::create_dir_all(shard_dir).await?;
+
+        let temp_path = shard_dir.join(format!(
+            ".{cid}.{}.{}.tmp",
+            process::id(),
+            TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        if let Err(err) = write_synced(&temp_path, &bytes).await {
+            let _ = fs::remove_file(&temp_path).await;
+            return Err(err);
+        }
+
+        fs::rename(&temp_path, &path).await?;
+
+        // The rename is only durable once the directory holding the new entry is synced.
+        sync_dir(shard_dir).await?;
+        if is_new_shard {
+            sync_dir(&self.root).await?;
+        }
+
+        Ok(cid)
+    }
+
+    /// Retrieves an array of bytes from the block store with given CID, checking that the bytes
+    /// hash to it.
+    async fn get_block<'a>(&'a self, cid: &Cid) -> Result<Cow<'a, Vec<u8>>> {
+        let bytes = match fs::read(self.block_path(cid)).await {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                bail!(FsError::CIDNotFoundInBlockstore)
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let code = Code::try_from(cid.hash().code())?;
+        if code.digest(&bytes) != *cid.hash() {
+            bail!(FsError::BlockContentMismatch(*cid));
+        }
+
+        Ok(Cow::Owned(bytes))
+    }
+}
+
+#[async_trait(?Send)]
+impl PrunableBlockStore for DiskBlockStore {
+    /// Lists the CIDs of all block files, skipping temporary files of unfinished writes.
+    async fn list_blocks(&self) -> Result<Vec<Cid>> {
+        let mut cids = Vec::new();
+        let mut shards = fs::read_dir(&self.root).await?;
+        while let Some(shard) = shards.try_next().await? {
+            if !shard.file_type().await?.is_dir() {
+                continue;
+            }
+
+            let mut entries = fs::read_dir(shard.path()).await?;
+            while let Some(entry) = entries.try_next().await? {
+                let name = entry.file_name();
+                match name.to_str() {
+                    Some(name) if !name.starts_with('.') => cids.push(Cid::try_from(name)?),
+                    _ => (),
+                }
+            }
+        }
+
+        Ok(cids)
+    }
+
+    /// Deletes the file of the block with given CID.
+    async fn delete_block(&mut self, cid: &Cid) -> Result<()> {
+        match fs::remove_file(self.block_path(cid)).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Writes bytes to a new file and flushes them to disk.
+async fn write_synced(path: &Path, bytes: &[u8]) -> Result<()> {
+    use async_std::io::WriteExt;
+
+    let mut file = fs::File::create(path).await?;
+    file.write_all(bytes).await?;
+    file.sync_all().await?;
+
+    Ok(())
+}
+
+/// Flushes the entries of a directory to disk. Directories can't be opened as files on Windows,
+/// where renames are made durable by the file system journal instead.
+async fn sync_dir(path: &Path) -> Result<()> {
+    if cfg!(unix) {
+        fs::File::open(path).await?.sync_all().await?;
+    }
+
+    Ok(())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod diskstore_tests {
+    use super::*;
+
+    #[async_std::test]
+    async fn blocks_survive_reopening_the_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = &mut DiskBlockStore::new(dir.path()).await.unwrap();
+
+        let first_cid = store
+            .put_block(vec![1, 2, 3, 4, 5], IpldCodec::Raw)
+            .await
+            .unwrap();
+        let second_cid = store
+            .put_block(b"hello world".to_vec(), IpldCodec::Raw)
+            .await
+            .unwrap();
+
+        // Putting a block twice is a no-op.
+        assert_eq!(
+            store
+                .put_block(vec![1, 2, 3, 4, 5], IpldCodec::Raw)
+                .await
+                .unwrap(),
+            first_cid
+        );
+
+        let store = DiskBlockStore::new(dir.path()).await.unwrap();
+
+        assert_eq!(
+            store.get_block(&first_cid).await.unwrap().as_ref(),
+            &vec![1, 2, 3, 4, 5]
+        );
+        assert_eq!(
+            store.get_block(&second_cid).await.unwrap().as_ref(),
+            b"hello world"
+        );
+
+        let missing = Cid::new_v1(IpldCodec::Raw.into(), Code::Sha2_256.digest(b"missing"));
+        let error = store.get_block(&missing).await.unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<FsError>(),
+            Some(FsError::CIDNotFoundInBlockstore)
+        ));
+    }
+
+    #[async_std::test]
+    async fn corrupted_blocks_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = &mut DiskBlockStore::new(dir.path()).await.unwrap();
+        let cid = store
+            .put_block(b"hello world".to_vec(), IpldCodec::Raw)
+            .await
+            .unwrap();
+
+        let path = store.block_path(&cid);
+        assert!(path.starts_with(dir.path()));
+
+        fs::write(&path, b"hello mars").await.unwrap();
+        let error = store.get_block(&cid).await.unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<FsError>(),
+            Some(FsError::BlockContentMismatch(_))
+        ));
+    }
+
+    #[async_std::test]
+    async fn blocks_can_be_listed_and_deleted() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = &mut DiskBlockStore::new(dir.path()).await.unwrap();
+        let first_cid = store
+            .put_block(vec![1, 2, 3], IpldCodec::Raw)
+            .await
+            .unwrap();
+        let second_cid = store
+            .put_block(vec![4, 5, 6], IpldCodec::Raw)
+            .await
+            .unwrap();
+
+        // Leftovers of an interrupted write are not blocks.
+        fs::write(dir.path().join(".leftover.tmp"), b"")
+            .await
+            .unwrap();
+
+        let mut cids = store.list_blocks().await.unwrap();
+        cids.sort();
+        let mut expected = vec![first_cid, second_cid];
+        expected.sort();
+
+        assert_eq!(cids, expected);
+
+        store.delete_block(&first_cid).await.unwrap();
+        store.delete_block(&first_cid).await.unwrap();
+
+        assert_eq!(store.list_blocks().await.unwrap(), vec![second_cid]);
+        assert!(store.get_block(&first_cid).await.is_err());
+    }
+}
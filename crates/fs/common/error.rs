@@ -61,6 +61,45 @@ pub enum FsError {
 
     #[error("Found unexpected node type: {0:?}")]
     UnexpectedNodeType(NodeType),
+
+    #[error("Cannot find file content block in private forest")]
+    FileShardNotFound,
+
+    #[error("Invalid UnixFS data: {0}")]
+    InvalidUnixFsData(String),
+
+    #[error("Unsupported content codec: {0:#x}")]
+    UnsupportedCodec(u64),
+
+    #[error("Invalid chunker parameters")]
+    InvalidChunker,
+
+    #[error("Unable to rewind ratchet: {0}")]
+    UnableToRewindRatchet(String),
+
+    #[error("Invalid exchange key length: {0}")]
+    InvalidExchangeKey(usize),
+
+    #[error("Invalid private link: {0}")]
+    InvalidPrivateLink(String),
+
+    #[error("Unsupported private link version: {0}")]
+    UnsupportedPrivateLinkVersion(u8),
+
+    #[error("Unsupported keychain version: {0}")]
+    UnsupportedKeychainVersion(semver::Version),
+
+    #[error("Unable to derive key: {0}")]
+    UnableToDeriveKey(String),
+
+    #[error("Unsupported password header version: {0}")]
+    UnsupportedPasswordHeaderVersion(semver::Version),
+
+    #[error("Block content does not match its CID: {0}")]
+    BlockContentMismatch(libipld::Cid),
+
+    #[error("Invalid CAR file: {0}")]
+    InvalidCarFile(String),
 }
 
 pub fn error<T>(err: impl std::error::Error + Send + Sync + 'static) -> Result<T> {
@@ -0,0 +1,170 @@
+//! Garbage collection of blocks that are no longer reachable.
+
+use anyhow::Result;
+use libipld::Cid;
+
+use super::{reachable_blocks, PrunableBlockStore};
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Deletes every block that is not reachable from the roots and returns the CIDs of the deleted
+/// blocks.
+///
+/// Every mutation of a filesystem stores new blocks and leaves the old ones behind, so stores grow
+/// until they are collected. With `keep_versions` set, only that many previous versions of public
+/// nodes are kept. See [`reachable_blocks`] for what is considered reachable.
+///
+/// All roots of a store have to be given, as blocks only reachable from a missing root are deleted.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Rc;
+///
+/// use chrono::Utc;
+/// use libipld::IpldCodec;
+/// use wnfs::{gc, BlockStore, MemoryBlockStore, PublicDirectory};
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &mut MemoryBlockStore::default();
+///     let root_cid = PublicDirectory::new(Utc::now()).store(store).await.unwrap();
+///     let orphan_cid = store.put_block(b"orphan".to_vec(), IpldCodec::Raw).await.unwrap();
+///
+///     let deleted = gc(&[root_cid], None, store).await.unwrap();
+///
+///     assert_eq!(deleted, vec![orphan_cid]);
+///     assert!(store.get_block(&root_cid).await.is_ok());
+/// }
+/// ```
+pub async fn gc<B: PrunableBlockStore>(
+    roots: &[Cid],
+    keep_versions: Option<usize>,
+    store: &mut B,
+) -> Result<Vec<Cid>> {
+    let reachable = reachable_blocks(roots, keep_versions, store).await?;
+
+    let mut deleted = Vec::new();
+    for cid in store.list_blocks().await? {
+        if !reachable.contains(&cid) {
+            store.delete_block(&cid).await?;
+            deleted.push(cid);
+        }
+    }
+
+    Ok(deleted)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod gc_tests {
+    use std::rc::Rc;
+
+    use chrono::Utc;
+    use libipld::IpldCodec;
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use test_log::test;
+
+    use super::*;
+    use crate::{
+        private::PrivateForest, public::unixfs::Chunker, BlockStore, MemoryBlockStore, Namefilter,
+        PrivateDirectory, PrivateOpResult, PublicDirectory, PublicOpResult,
+    };
+
+    /// Writes a new version of a file in the directory, based on the history of the directory.
+    async fn write_version(
+        root_dir: Rc<PublicDirectory>,
+        content: &[u8],
+        store: &mut MemoryBlockStore,
+    ) -> (Rc<PublicDirectory>, Cid) {
+        let PublicOpResult { root_dir: next, .. } = Rc::clone(&root_dir)
+            .write_content(
+                &["notes.txt".into()],
+                content.to_vec(),
+                &Chunker::default(),
+                Utc::now(),
+                store,
+            )
+            .await
+            .unwrap();
+        let PublicOpResult { root_dir: next, .. } =
+            next.base_history_on(root_dir, store).await.unwrap();
+        let cid = next.store(store).await.unwrap();
+
+        (next, cid)
+    }
+
+    #[test(async_std::test)]
+    async fn gc_deletes_unreachable_blocks_and_old_versions() {
+        let store = &mut MemoryBlockStore::default();
+        let root_dir = Rc::new(PublicDirectory::new(Utc::now()));
+        let (root_dir, first_cid) = write_version(root_dir, b"first", store).await;
+        let (root_dir, second_cid) = write_version(root_dir, b"second", store).await;
+        let (_, third_cid) = write_version(root_dir, b"third", store).await;
+        let orphan_cid = store
+            .put_block(b"orphan".to_vec(), IpldCodec::Raw)
+            .await
+            .unwrap();
+
+        let deleted = gc(&[third_cid], None, store).await.unwrap();
+
+        assert!(deleted.contains(&orphan_cid));
+        for cid in [first_cid, second_cid, third_cid] {
+            assert!(store.get_block(&cid).await.is_ok());
+        }
+
+        let deleted = gc(&[third_cid], Some(1), store).await.unwrap();
+
+        assert!(deleted.contains(&first_cid));
+        assert!(store.get_block(&first_cid).await.is_err());
+
+        let second: PublicDirectory = store.get_deserializable(&second_cid).await.unwrap();
+        let PublicOpResult { result, .. } = Rc::new(second)
+            .read_content(&["notes.txt".into()], store)
+            .await
+            .unwrap();
+
+        assert_eq!(result, b"second");
+        assert!(gc(&[third_cid], Some(1), store).await.unwrap().is_empty());
+    }
+
+    #[test(async_std::test)]
+    async fn gc_keeps_private_forest_blocks() {
+        let store = &mut MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        let PrivateOpResult { root_dir, hamt, .. } = root_dir
+            .write(
+                &["text.txt".into()],
+                true,
+                Utc::now(),
+                b"hello world".to_vec(),
+                Rc::new(PrivateForest::new()),
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+        let forest_cid = store.put_async_serializable(&hamt).await.unwrap();
+
+        gc(&[forest_cid], Some(0), store).await.unwrap();
+
+        let hamt: PrivateForest = store.get_deserializable(&forest_cid).await.unwrap();
+        let PrivateOpResult { result, .. } = root_dir
+            .read(&["text.txt".into()], true, Rc::new(hamt), store)
+            .await
+            .unwrap();
+
+        assert_eq!(result, b"hello world");
+    }
+}
@@ -1,10 +1,16 @@
-use anyhow::Result;
+use std::io::Cursor;
+
+use anyhow::{ensure, Result};
 use async_once_cell::OnceCell;
 use async_trait::async_trait;
-use libipld::Cid;
+use libipld::{
+    cbor::DagCborCodec,
+    codec::{Decode, Encode},
+    serde as ipld_serde, Cid, Ipld, IpldCodec,
+};
 use serde::de::DeserializeOwned;
 
-use crate::{AsyncSerialize, BlockStore, IpldEq};
+use crate::{AsyncSerialize, BlockStore, FsError, IpldEq};
 
 //--------------------------------------------------------------------------------------------------
 // Type Definitions
@@ -47,14 +53,66 @@ impl<T> Link<T> {
     {
         match self {
             Self::Encoded { cid, .. } => Ok(cid),
-            Self::Decoded { value, cid_cache } => {
-                cid_cache
-                    .get_or_try_init(async { store.put_async_serializable(value).await })
-                    .await
+            Self::Decoded { cid_cache, .. } => {
+                if let Some(cid) = cid_cache.get() {
+                    return Ok(cid);
+                }
+
+                let cid = Self::resolve_cids(&[self], store).await?[0];
+                Ok(cid_cache.get_or_init(async { cid }).await)
             }
         }
     }
 
+    /// Gets the Cids of many links, in order.
+    ///
+    /// The values of links that don't have a Cid yet are stored with a single call to
+    /// [`BlockStore::put_blocks`], so a store can put them all at once.
+    pub async fn resolve_cids<B: BlockStore + ?Sized>(
+        links: &[&Self],
+        store: &mut B,
+    ) -> Result<Vec<Cid>>
+    where
+        T: AsyncSerialize,
+    {
+        let mut blocks = Vec::new();
+        let mut pending = Vec::new();
+        for link in links {
+            if let Self::Decoded { value, cid_cache } = link {
+                if cid_cache.get().is_none() {
+                    let mut bytes = Vec::new();
+                    value
+                        .async_serialize_ipld(store)
+                        .await?
+                        .encode(DagCborCodec, &mut bytes)?;
+
+                    blocks.push((bytes, IpldCodec::DagCbor));
+                    pending.push(cid_cache);
+                }
+            }
+        }
+
+        // Skip the store when every Cid is cached, as putting no blocks can still cost a write.
+        if !blocks.is_empty() {
+            let cids = store.put_blocks(blocks).await?;
+            ensure!(
+                cids.len() == pending.len(),
+                FsError::InvalidSerialisation(
+                    "Block store returned a different number of Cids than blocks"
+                )
+            );
+
+            for (cid_cache, cid) in pending.into_iter().zip(cids) {
+                cid_cache.get_or_init(async { cid }).await;
+            }
+        }
+
+        Ok(links
+            .iter()
+            .filter_map(|link| link.get_cid().copied())
+            .collect())
+    }
+
     /// Gets the value stored in link. It attempts to get it from the store if it is not present in link.
     pub async fn resolve_value<B: BlockStore>(&self, store: &B) -> Result<&T>
     where
@@ -70,6 +128,49 @@ impl<T> Link<T> {
         }
     }
 
+    /// Gets the values of many links, in order.
+    ///
+    /// The values that aren't cached yet are fetched with a single call to
+    /// [`BlockStore::get_blocks`], so a store can get them all at once.
+    pub async fn resolve_values<'a, B: BlockStore>(
+        links: &[&'a Self],
+        store: &B,
+    ) -> Result<Vec<&'a T>>
+    where
+        T: DeserializeOwned,
+    {
+        let mut cids = Vec::new();
+        let mut pending = Vec::new();
+        for link in links {
+            if let Self::Encoded { cid, value_cache } = link {
+                if value_cache.get().is_none() {
+                    cids.push(*cid);
+                    pending.push(value_cache);
+                }
+            }
+        }
+
+        if !cids.is_empty() {
+            let blocks = store.get_blocks(&cids).await?;
+            ensure!(
+                blocks.len() == pending.len(),
+                FsError::InvalidDeserialization(format!(
+                    "Expected {} blocks from block store, got {}",
+                    pending.len(),
+                    blocks.len()
+                ))
+            );
+
+            for (value_cache, bytes) in pending.into_iter().zip(blocks) {
+                let ipld = Ipld::decode(DagCborCodec, &mut Cursor::new(bytes.as_ref()))?;
+                let value = ipld_serde::from_ipld(ipld)?;
+                value_cache.get_or_init(async { value }).await;
+            }
+        }
+
+        Ok(links.iter().filter_map(|link| link.get_value()).collect())
+    }
+
     /// Gets the cid data stored in type.
     ///
     /// NOTE: This does not attempt to get it from the store if it does not exist..
@@ -210,8 +311,45 @@ where
 
 #[cfg(test)]
 mod ipld_link_tests {
+    use std::{borrow::Cow, cell::Cell};
+
+    use anyhow::Result;
+    use async_trait::async_trait;
+    use libipld::{Cid, IpldCodec};
+
     use crate::{BlockStore, Link, MemoryBlockStore};
 
+    /// A block store that counts the calls made to it.
+    #[derive(Default)]
+    struct CountingBlockStore {
+        inner: MemoryBlockStore,
+        puts: usize,
+        gets: Cell<usize>,
+    }
+
+    #[async_trait(?Send)]
+    impl BlockStore for CountingBlockStore {
+        async fn put_block(&mut self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+            self.puts += 1;
+            self.inner.put_block(bytes, codec).await
+        }
+
+        async fn get_block<'a>(&'a self, cid: &Cid) -> Result<Cow<'a, Vec<u8>>> {
+            self.gets.set(self.gets.get() + 1);
+            self.inner.get_block(cid).await
+        }
+
+        async fn put_blocks(&mut self, blocks: Vec<(Vec<u8>, IpldCodec)>) -> Result<Vec<Cid>> {
+            self.puts += 1;
+            self.inner.put_blocks(blocks).await
+        }
+
+        async fn get_blocks<'a>(&'a self, cids: &[Cid]) -> Result<Vec<Cow<'a, Vec<u8>>>> {
+            self.gets.set(self.gets.get() + 1);
+            self.inner.get_blocks(cids).await
+        }
+    }
+
     #[async_std::test]
     async fn link_value_can_be_resolved() {
         let store = &mut MemoryBlockStore::default();
@@ -237,4 +375,55 @@ mod ipld_link_tests {
 
         assert_eq!(value, pair);
     }
+
+    #[async_std::test]
+    async fn links_are_resolved_in_one_batch() {
+        let store = &mut CountingBlockStore::default();
+        let stored_cid = store.inner.put_serializable(&1).await.unwrap();
+        let links = [
+            Link::<u64>::from_cid(stored_cid),
+            Link::from(2),
+            Link::from(3),
+            Link::from(4),
+        ];
+
+        let cids = Link::resolve_cids(&links.iter().collect::<Vec<_>>(), store)
+            .await
+            .unwrap();
+
+        assert_eq!(store.puts, 1);
+        assert_eq!(cids[0], stored_cid);
+        assert_eq!(
+            cids[2],
+            *Link::from(3).resolve_cid(&mut store.inner).await.unwrap()
+        );
+
+        let links = cids
+            .into_iter()
+            .map(Link::<u64>::from_cid)
+            .collect::<Vec<_>>();
+        let values = Link::resolve_values(&links.iter().collect::<Vec<_>>(), store)
+            .await
+            .unwrap();
+
+        assert_eq!(store.gets.get(), 1);
+        assert_eq!(values, vec![&1, &2, &3, &4]);
+
+        // Links that are already resolved don't touch the store again.
+        Link::resolve_cids(&links.iter().collect::<Vec<_>>(), store)
+            .await
+            .unwrap();
+        Link::resolve_values(&links.iter().collect::<Vec<_>>(), store)
+            .await
+            .unwrap();
+
+        assert_eq!(store.puts, 1);
+        assert_eq!(store.gets.get(), 1);
+
+        let link = Link::from(5);
+        link.resolve_cid(store).await.unwrap();
+        link.resolve_cid(store).await.unwrap();
+
+        assert_eq!(store.puts, 2);
+    }
 }
@@ -1,19 +1,34 @@
 mod async_serialize;
 pub mod blockstore;
+pub mod car;
+mod diff;
+#[cfg(feature = "disk-store")]
+mod diskstore;
 mod encoding;
 mod error;
+mod gc;
 mod link;
 mod metadata;
 mod pathnodes;
+#[cfg(feature = "redb-store")]
+mod redbstore;
 pub mod utils;
+mod walk;
 
 pub use async_serialize::*;
 pub use blockstore::*;
+pub use diff::*;
+#[cfg(feature = "disk-store")]
+pub use diskstore::*;
 pub use encoding::*;
 pub use error::*;
+pub use gc::*;
 pub use link::*;
 pub use metadata::*;
 pub use pathnodes::*;
+#[cfg(feature = "redb-store")]
+pub use redbstore::*;
+pub use walk::*;
 
 //--------------------------------------------------------------------------------------------------
 // Type Definitions
@@ -0,0 +1,271 @@
+//! A block store backed by an embedded redb database.
+
+use std::{borrow::Cow, path::Path};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use libipld::{cid::Version, Cid, IpldCodec};
+use multihash::{Code, MultihashDigest};
+use redb::{Database, ReadableTable, TableDefinition};
+
+use super::{BlockStore, FsError, PrunableBlockStore};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The table blocks are stored in, keyed by their CID bytes.
+const BLOCKS: TableDefinition<&[u8], &[u8]> = TableDefinition::new("blocks");
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A block store that keeps blocks in a single-file redb database.
+///
+/// Every write is a transaction that is durable once it returns, and a write that is interrupted,
+/// e.g. by a crash, leaves no trace. Many blocks can be written in one transaction with
+/// [`BlockStore::put_blocks`].
+///
+/// # Examples
+///
+/// ```
+/// use libipld::IpldCodec;
+/// use wnfs::{BlockStore, RedbBlockStore};
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let dir = tempfile::tempdir().unwrap();
+///     let store = &mut RedbBlockStore::new(dir.path().join("blocks.redb")).unwrap();
+///
+///     let cid = store.put_block(b"hello world".to_vec(), IpldCodec::Raw).await.unwrap();
+///     let bytes = store.get_block(&cid).await.unwrap();
+///
+///     assert_eq!(bytes.as_ref(), b"hello world");
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RedbBlockStore {
+    db: Database,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl RedbBlockStore {
+    /// Opens the database at the given path, creating it if it does not exist.
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let db = Database::create(path)?;
+
+        // Create the table up front, so reads never find it missing.
+        let txn = db.begin_write()?;
+        txn.open_table(BLOCKS)?;
+        txn.commit()?;
+
+        Ok(Self { db })
+    }
+
+    /// Compacts the database file, returning whether any space was reclaimed. Compaction can
+    /// take a while, so it is left to the caller to run it when the store is idle.
+    pub fn compact(&mut self) -> Result<bool> {
+        Ok(self.db.compact()?)
+    }
+}
+
+#[async_trait(?Send)]
+impl BlockStore for RedbBlockStore {
+    /// Stores an array of bytes in the block store.
+    async fn put_block(&mut self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid> {
+        let mut cids = self.put_blocks(vec![(bytes, codec)]).await?;
+        Ok(cids.remove(0))
+    }
+
+    /// Retrieves an array of bytes from the block store with given CID.
+    async fn get_block<'a>(&'a self, cid: &Cid) -> Result<Cow<'a, Vec<u8>>> {
+        let mut blocks = self.get_blocks(&[*cid]).await?;
+        Ok(blocks.remove(0))
+    }
+
+    /// Retrieves many blocks in a single read transaction.
+    async fn get_blocks<'a>(&'a self, cids: &[Cid]) -> Result<Vec<Cow<'a, Vec<u8>>>> {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(BLOCKS)?;
+
+        cids.iter()
+            .map(|cid| {
+                let bytes = table
+                    .get(cid.to_bytes().as_slice())?
+                    .ok_or(FsError::CIDNotFoundInBlockstore)?
+                    .value()
+                    .to_vec();
+
+                Ok(Cow::Owned(bytes))
+            })
+            .collect()
+    }
+
+    /// Stores many arrays of bytes in a single transaction, returning their CIDs in order.
+    /// Either all of the blocks are stored or none are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libipld::IpldCodec;
+    /// use wnfs::{BlockStore, RedbBlockStore};
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let dir = tempfile::tempdir().unwrap();
+    ///     let store = &mut RedbBlockStore::new(dir.path().join("blocks.redb")).unwrap();
+    ///
+    ///     let cids = store
+    ///         .put_blocks(vec![
+    ///             (b"hello".to_vec(), IpldCodec::Raw),
+    ///             (b"world".to_vec(), IpldCodec::Raw),
+    ///         ])
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(store.get_block(&cids[1]).await.unwrap().as_ref(), b"world");
+    /// }
+    /// ```
+    async fn put_blocks(&mut self, blocks: Vec<(Vec<u8>, IpldCodec)>) -> Result<Vec<Cid>> {
+        let txn = self.db.begin_write()?;
+        let mut cids = Vec::with_capacity(blocks.len());
+        {
+            let mut table = txn.open_table(BLOCKS)?;
+            for (bytes, codec) in blocks {
+                let hash = Code::Sha2_256.digest(&bytes);
+                let cid = Cid::new(Version::V1, codec.into(), hash)?;
+
+                table.insert(cid.to_bytes().as_slice(), bytes.as_slice())?;
+                cids.push(cid);
+            }
+        }
+        txn.commit()?;
+
+        Ok(cids)
+    }
+}
+
+#[async_trait(?Send)]
+impl PrunableBlockStore for RedbBlockStore {
+    /// Lists the CIDs of all blocks in the database.
+    async fn list_blocks(&self) -> Result<Vec<Cid>> {
+        let txn = self.db.begin_read()?;
+        let table = txn.open_table(BLOCKS)?;
+
+        table
+            .iter()?
+            .map(|entry| Ok(Cid::try_from(entry?.0.value())?))
+            .collect()
+    }
+
+    /// Deletes the block with given CID from the database.
+    async fn delete_block(&mut self, cid: &Cid) -> Result<()> {
+        let txn = self.db.begin_write()?;
+        txn.open_table(BLOCKS)?.remove(cid.to_bytes().as_slice())?;
+        txn.commit()?;
+
+        Ok(())
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod redbstore_tests {
+    use super::*;
+
+    #[async_std::test]
+    async fn blocks_are_durable_across_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocks.redb");
+        let mut store = RedbBlockStore::new(&path).unwrap();
+
+        let cid = store
+            .put_block(b"hello world".to_vec(), IpldCodec::Raw)
+            .await
+            .unwrap();
+        let cids = store
+            .put_blocks(vec![
+                (vec![1, 2, 3], IpldCodec::Raw),
+                (vec![4, 5, 6], IpldCodec::DagCbor),
+            ])
+            .await
+            .unwrap();
+        drop(store);
+
+        let mut store = RedbBlockStore::new(&path).unwrap();
+        store.compact().unwrap();
+
+        assert_eq!(
+            store.get_block(&cid).await.unwrap().as_ref(),
+            b"hello world"
+        );
+        assert_eq!(
+            store.get_block(&cids[0]).await.unwrap().as_ref(),
+            &vec![1, 2, 3]
+        );
+        assert_eq!(
+            store.get_block(&cids[1]).await.unwrap().as_ref(),
+            &vec![4, 5, 6]
+        );
+    }
+
+    #[async_std::test]
+    async fn interrupted_writes_leave_no_blocks_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blocks.redb");
+        let store = RedbBlockStore::new(&path).unwrap();
+        let bytes = b"never committed".to_vec();
+        let cid = Cid::new_v1(IpldCodec::Raw.into(), Code::Sha2_256.digest(&bytes));
+
+        // Write a block without committing, as if the process died mid-transaction.
+        {
+            let txn = store.db.begin_write().unwrap();
+            let mut table = txn.open_table(BLOCKS).unwrap();
+            table
+                .insert(cid.to_bytes().as_slice(), bytes.as_slice())
+                .unwrap();
+        }
+        drop(store);
+
+        let store = RedbBlockStore::new(&path).unwrap();
+        let error = store.get_block(&cid).await.unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<FsError>(),
+            Some(FsError::CIDNotFoundInBlockstore)
+        ));
+    }
+
+    #[async_std::test]
+    async fn blocks_can_be_listed_and_deleted() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = &mut RedbBlockStore::new(dir.path().join("blocks.redb")).unwrap();
+        let cids = store
+            .put_blocks(vec![
+                (vec![1, 2, 3], IpldCodec::Raw),
+                (vec![4, 5, 6], IpldCodec::Raw),
+            ])
+            .await
+            .unwrap();
+
+        let mut listed = store.list_blocks().await.unwrap();
+        listed.sort();
+        let mut expected = cids.clone();
+        expected.sort();
+
+        assert_eq!(listed, expected);
+
+        store.delete_block(&cids[0]).await.unwrap();
+        store.delete_block(&cids[0]).await.unwrap();
+
+        assert_eq!(store.list_blocks().await.unwrap(), vec![cids[1]]);
+        assert!(store.get_block(&cids[0]).await.is_err());
+    }
+}
@@ -42,6 +42,14 @@ pub(crate) fn split_last(path_segments: &[String]) -> Result<(&[String], &String
     }
 }
 
+/// Gets the part of the bytes that falls within the given range. The range is clamped to the
+/// bounds of the bytes.
+pub(crate) fn get_range(bytes: &[u8], offset: usize, len: usize) -> &[u8] {
+    let start = offset.min(bytes.len());
+    let end = offset.saturating_add(len).min(bytes.len());
+    &bytes[start..end]
+}
+
 /// Generates a random byte array of the given length.
 ///
 /// # Examples
@@ -76,4 +84,13 @@ mod public_directory_tests {
         assert_eq!(rest, &["a", "b"]);
         assert_eq!(last, &"c");
     }
+
+    #[test]
+    fn get_range_clamps_range_to_bytes() {
+        let bytes = b"hello world";
+        assert_eq!(get_range(bytes, 6, 5), b"world");
+        assert_eq!(get_range(bytes, 6, 100), b"world");
+        assert_eq!(get_range(bytes, 100, 5), b"");
+        assert_eq!(get_range(bytes, 3, usize::MAX), b"lo world");
+    }
 }
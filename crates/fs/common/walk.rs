@@ -0,0 +1,138 @@
+//! Traversal of the blocks reachable from a set of roots.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::Cursor,
+};
+
+use anyhow::{bail, Result};
+use libipld::{
+    cbor::DagCborCodec,
+    codec::{Codec, Decode},
+    pb::PbNode,
+    Cid, Ipld, IpldCodec,
+};
+
+use crate::{BlockStore, FsError};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The field public nodes link to their previous versions with.
+const PREVIOUS_FIELD: &str = "previous";
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Gets the CIDs a block links to, in the order they appear in the block.
+///
+/// DAG-CBOR blocks, like public nodes and private forest nodes, link to other blocks with IPLD
+/// links. DAG-PB blocks, like UnixFS file nodes, link to their children. Raw blocks, like encrypted
+/// private nodes and file content, are leaves.
+pub(crate) fn block_links(cid: &Cid, bytes: &[u8]) -> Result<Vec<Cid>> {
+    match IpldCodec::try_from(cid.codec()) {
+        Ok(IpldCodec::Raw) => Ok(vec![]),
+        Ok(IpldCodec::DagCbor) => {
+            let mut links = vec![];
+            DagCborCodec.references::<Ipld, _>(bytes, &mut links)?;
+            Ok(links)
+        }
+        Ok(IpldCodec::DagPb) => Ok(PbNode::from_bytes(bytes)?
+            .links
+            .into_iter()
+            .map(|link| link.cid)
+            .collect()),
+        _ => bail!(FsError::UnsupportedCodec(cid.codec())),
+    }
+}
+
+/// Gets every CID reachable from the roots, including the roots themselves.
+///
+/// Roots are usually the CIDs of stored public directories and private forests. Links are followed
+/// through DAG-CBOR and DAG-PB blocks, so public file content and the encrypted nodes and content
+/// of a private forest are included.
+///
+/// With `keep_versions` set, at most that many previous versions of a public node are followed
+/// from its current version. Private nodes are encrypted, so all of their revisions that are kept
+/// in a forest stay reachable.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Rc;
+///
+/// use chrono::Utc;
+/// use wnfs::{reachable_blocks, MemoryBlockStore, PublicDirectory, PublicOpResult};
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &mut MemoryBlockStore::default();
+///     let PublicOpResult { root_dir, .. } = Rc::new(PublicDirectory::new(Utc::now()))
+///         .mkdir(&["pictures".into()], Utc::now(), store)
+///         .await
+///         .unwrap();
+///     let root_cid = root_dir.store(store).await.unwrap();
+///
+///     let reachable = reachable_blocks(&[root_cid], None, store).await.unwrap();
+///
+///     // The root directory and the pictures directory.
+///     assert_eq!(reachable.len(), 2);
+/// }
+/// ```
+pub async fn reachable_blocks<B: BlockStore>(
+    roots: &[Cid],
+    keep_versions: Option<usize>,
+    store: &B,
+) -> Result<HashSet<Cid>> {
+    // The fewest previous links that were followed to reach each block. A block reached with
+    // fewer of them is walked again, as more of its history may be kept from there.
+    let mut visited = HashMap::<Cid, usize>::new();
+    let mut stack = roots.iter().map(|cid| (*cid, 0)).collect::<Vec<_>>();
+    while let Some((cid, versions)) = stack.pop() {
+        if matches!(visited.get(&cid), Some(seen) if *seen <= versions) {
+            continue;
+        }
+        visited.insert(cid, versions);
+
+        let bytes = store.get_block(&cid).await?;
+        let (links, previous) = version_links(&cid, &bytes)?;
+
+        stack.extend(links.into_iter().map(|link| (link, versions)));
+        match keep_versions {
+            None => stack.extend(previous.into_iter().map(|link| (link, versions))),
+            Some(keep) if versions < keep => {
+                stack.extend(previous.into_iter().map(|link| (link, versions + 1)))
+            }
+            _ => (),
+        }
+    }
+
+    Ok(visited.into_keys().collect())
+}
+
+/// Gets the CIDs a block links to, split into links to previous versions and all other links.
+fn version_links(cid: &Cid, bytes: &[u8]) -> Result<(Vec<Cid>, Vec<Cid>)> {
+    if cid.codec() != u64::from(IpldCodec::DagCbor) {
+        return Ok((block_links(cid, bytes)?, vec![]));
+    }
+
+    let mut ipld = Ipld::decode(DagCborCodec, &mut Cursor::new(bytes))?;
+    let previous = match &mut ipld {
+        Ipld::Map(map) => map.remove(PREVIOUS_FIELD).map(|ipld| ipld_links(&ipld)),
+        _ => None,
+    };
+
+    Ok((ipld_links(&ipld), previous.unwrap_or_default()))
+}
+
+/// Gets the links nested in an IPLD value.
+fn ipld_links(ipld: &Ipld) -> Vec<Cid> {
+    ipld.iter()
+        .filter_map(|ipld| match ipld {
+            Ipld::Link(cid) => Some(*cid),
+            _ => None,
+        })
+        .collect()
+}
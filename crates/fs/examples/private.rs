@@ -1,5 +1,6 @@
 //! This example shows how to add a directory to a private forest (also HAMT) which encrypts it.
-//! It also shows how to retrieve encrypted nodes from the forest using `PrivateRef`s.
+//! It also shows how to retrieve encrypted nodes from the forest using `PrivateRef`s, and how to
+//! persist those `PrivateRef`s in a `Keychain` encrypted with a master key.
 
 use chrono::Utc;
 use libipld::Cid;
@@ -7,7 +8,7 @@ use rand::{thread_rng, RngCore};
 use std::rc::Rc;
 use wnfs::{
     dagcbor,
-    private::{PrivateForest, PrivateRef},
+    private::{Key, Keychain, PrivateForest, PrivateRef},
     BlockStore, MemoryBlockStore, Namefilter, PrivateDirectory, PrivateOpResult,
 };
 
@@ -22,6 +23,19 @@ async fn main() {
     // Create a new private forest and get the cid to it.
     let (forest_cid, private_ref) = get_forest_cid_and_private_ref(store, rng).await;
 
+    // Keep the private ref in a keychain encrypted with the user's master key, so only the master
+    // key and the keychain's cid need to be remembered.
+    let master_key = Key::new(rand::random());
+    let mut keychain = Keychain::new();
+    keychain.insert("root", private_ref);
+    let keychain_cid = keychain.store(&master_key, store, rng).await.unwrap();
+
+    // Load the keychain back and get the private ref of the root directory.
+    let keychain = Keychain::load(&keychain_cid, &master_key, store)
+        .await
+        .unwrap();
+    let private_ref = keychain.get("root").unwrap();
+
     // Fetch CBOR bytes of private forest from the blockstore.
     let cbor_bytes = store
         .get_deserializable::<Vec<u8>>(&forest_cid)
@@ -32,7 +46,7 @@ async fn main() {
     let forest = dagcbor::decode::<PrivateForest>(cbor_bytes.as_ref()).unwrap();
 
     // Fetch and decrypt a directory from the private forest using provided private ref.
-    let dir = forest.get(&private_ref, store).await.unwrap();
+    let dir = forest.get(private_ref, store).await.unwrap();
 
     // Print the directory.
     println!("{:#?}", dir);
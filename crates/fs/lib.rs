@@ -18,7 +18,9 @@ pub use private::{
     namefilter::Namefilter,
     PrivateDirectory, PrivateFile, PrivateNode, PrivateOpResult,
 };
-pub use public::{PublicDirectory, PublicFile, PublicNode, PublicOpResult};
+pub use public::{
+    MergeConflict, PublicDirectory, PublicFile, PublicNode, PublicNodeVersion, PublicOpResult,
+};
 pub use traits::*;
 
 //--------------------------------------------------------------------------------------------------
@@ -33,6 +35,10 @@ pub mod ipld {
     };
 }
 
+pub mod ratchet {
+    pub use skip_ratchet::Ratchet;
+}
+
 pub mod rng {
     pub use rand_core::RngCore;
 }
@@ -0,0 +1,255 @@
+//! Diffs of private directories.
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use async_recursion::async_recursion;
+
+use crate::{BlockStore, ChangeType, FsError, NodeChange, NodeType};
+
+use super::{PrivateDirectory, PrivateForest, PrivateNode, PrivateRef};
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl PrivateDirectory {
+    /// Gets the files and directories that differ between this directory and another version of
+    /// it. Both versions are read from the given forest. Entries that have the same private ref in
+    /// both versions are skipped without being decrypted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{
+    ///     private::PrivateForest, ChangeType, MemoryBlockStore, Namefilter, NodeType,
+    ///     PrivateDirectory, PrivateOpResult,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let hamt = Rc::new(PrivateForest::new());
+    ///     let dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+    ///
+    ///     let PrivateOpResult { root_dir, hamt, .. } = Rc::clone(&dir)
+    ///         .write(&["notes.txt".into()], true, Utc::now(), b"hello".to_vec(), hamt, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let changes = root_dir.diff(&dir, &hamt, store).await.unwrap();
+    ///
+    ///     assert_eq!(changes.len(), 1);
+    ///     assert_eq!(changes[0].r#type, ChangeType::Add);
+    ///     assert_eq!(changes[0].path, vec!["notes.txt".to_string()]);
+    ///     assert_eq!(changes[0].node_type, NodeType::PrivateFile);
+    /// }
+    /// ```
+    pub async fn diff<B: BlockStore>(
+        &self,
+        other: &Self,
+        hamt: &PrivateForest,
+        store: &B,
+    ) -> Result<Vec<NodeChange>> {
+        let mut changes = Vec::new();
+        self.diff_helper(other, &mut Vec::new(), &mut changes, hamt, store)
+            .await?;
+        Ok(changes)
+    }
+
+    #[async_recursion(?Send)]
+    async fn diff_helper<B>(
+        &self,
+        other: &Self,
+        path: &mut Vec<String>,
+        changes: &mut Vec<NodeChange>,
+        hamt: &PrivateForest,
+        store: &B,
+    ) -> Result<()>
+    where
+        B: BlockStore,
+    {
+        let names = self
+            .entries
+            .keys()
+            .chain(other.entries.keys())
+            .collect::<BTreeSet<_>>();
+
+        for name in names {
+            path.push(name.clone());
+            match (self.entries.get(name), other.entries.get(name)) {
+                (Some(private_ref), Some(other_ref)) => {
+                    if private_ref != other_ref {
+                        let node = Self::get_node_by_ref(private_ref, hamt, store).await?;
+                        let other_node = Self::get_node_by_ref(other_ref, hamt, store).await?;
+                        match (&node, &other_node) {
+                            (PrivateNode::Dir(dir), PrivateNode::Dir(other_dir)) => {
+                                Self::push_change(ChangeType::Modify, path, &node, changes);
+                                dir.diff_helper(other_dir, path, changes, hamt, store)
+                                    .await?;
+                            }
+                            (PrivateNode::File(_), PrivateNode::File(_)) => {
+                                Self::push_change(ChangeType::Modify, path, &node, changes);
+                            }
+                            _ => {
+                                Self::push_change(ChangeType::Remove, path, &other_node, changes);
+                                Self::push_change(ChangeType::Add, path, &node, changes);
+                            }
+                        }
+                    }
+                }
+                (Some(private_ref), None) => {
+                    let node = Self::get_node_by_ref(private_ref, hamt, store).await?;
+                    Self::push_change(ChangeType::Add, path, &node, changes);
+                }
+                (None, Some(other_ref)) => {
+                    let other_node = Self::get_node_by_ref(other_ref, hamt, store).await?;
+                    Self::push_change(ChangeType::Remove, path, &other_node, changes);
+                }
+                (None, None) => unreachable!(),
+            }
+            path.pop();
+        }
+
+        Ok(())
+    }
+
+    async fn get_node_by_ref<B: BlockStore>(
+        private_ref: &PrivateRef,
+        hamt: &PrivateForest,
+        store: &B,
+    ) -> Result<PrivateNode> {
+        hamt.get(private_ref, store)
+            .await?
+            .ok_or_else(|| FsError::NotFound.into())
+    }
+
+    fn push_change(
+        r#type: ChangeType,
+        path: &[String],
+        node: &PrivateNode,
+        changes: &mut Vec<NodeChange>,
+    ) {
+        let node_type = match node {
+            PrivateNode::File(_) => NodeType::PrivateFile,
+            PrivateNode::Dir(_) => NodeType::PrivateDirectory,
+        };
+
+        changes.push(NodeChange {
+            r#type,
+            path: path.to_vec(),
+            node_type,
+        });
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod private_diff_tests {
+    use std::rc::Rc;
+
+    use chrono::Utc;
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use test_log::test;
+
+    use super::*;
+    use crate::{MemoryBlockStore, Namefilter, PrivateOpResult};
+
+    #[test(async_std::test)]
+    async fn diff_reports_changes_in_nested_directories() {
+        let store = &mut MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let mut hamt = Rc::new(PrivateForest::new());
+        let mut other = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        for path in [
+            vec!["pictures", "cats", "tabby.png"],
+            vec!["pictures", "dogs", "pug.png"],
+            vec!["notes.txt"],
+        ] {
+            let path = path.into_iter().map(String::from).collect::<Vec<_>>();
+            let result = other
+                .write(&path, true, Utc::now(), vec![1], hamt, store, rng)
+                .await
+                .unwrap();
+
+            other = result.root_dir;
+            hamt = result.hamt;
+        }
+
+        let PrivateOpResult { root_dir, hamt, .. } = Rc::clone(&other)
+            .write(
+                &["pictures".into(), "cats".into(), "tabby.png".into()],
+                true,
+                Utc::now(),
+                vec![2],
+                hamt,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        let PrivateOpResult { root_dir, hamt, .. } = root_dir
+            .rm(&["notes.txt".into()], true, hamt, store, rng)
+            .await
+            .unwrap();
+
+        let PrivateOpResult {
+            root_dir: main,
+            hamt,
+            ..
+        } = root_dir
+            .mkdir(&["music".into()], true, Utc::now(), hamt, store, rng)
+            .await
+            .unwrap();
+
+        let changes = main
+            .diff(&other, &hamt, store)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|change| (change.path.join("/"), change.r#type, change.node_type))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            changes,
+            vec![
+                ("music".into(), ChangeType::Add, NodeType::PrivateDirectory),
+                (
+                    "notes.txt".into(),
+                    ChangeType::Remove,
+                    NodeType::PrivateFile
+                ),
+                (
+                    "pictures".into(),
+                    ChangeType::Modify,
+                    NodeType::PrivateDirectory
+                ),
+                (
+                    "pictures/cats".into(),
+                    ChangeType::Modify,
+                    NodeType::PrivateDirectory
+                ),
+                (
+                    "pictures/cats/tabby.png".into(),
+                    ChangeType::Modify,
+                    NodeType::PrivateFile
+                ),
+            ]
+        );
+    }
+}
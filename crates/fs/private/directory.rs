@@ -1,20 +1,31 @@
 use std::{collections::BTreeMap, rc::Rc};
 
 use anyhow::{bail, ensure, Result};
+use async_stream::try_stream;
 use chrono::{DateTime, Utc};
+use futures::{future, stream, stream::LocalBoxStream, Stream};
 use rand_core::RngCore;
 use semver::Version;
 use serde::{de::Error as DeError, ser::Error as SerError, Deserialize, Deserializer, Serialize};
 
 use super::{
-    namefilter::Namefilter, Key, PrivateFile, PrivateForest, PrivateNode, PrivateNodeHeader,
-    PrivateRef, RatchetKey,
+    namefilter::Namefilter, CipherSuite, PrivateFile, PrivateForest, PrivateNode,
+    PrivateNodeHeader, PrivateRef, PrivateRefSerde, RatchetKey, SnapshotRef,
 };
 
 use crate::{
-    dagcbor, error, utils, BlockStore, FsError, Id, Metadata, NodeType, PathNodes, PathNodesResult,
+    dagcbor, error, utils, BlockStore, FsError, HashOutput, Id, Metadata, NodeType, PathNodes,
+    PathNodesResult,
 };
 
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The version of the private directory format. Directories from before 0.3.0 store the ratchet
+/// keys of their entries unencrypted.
+const PRIVATE_DIRECTORY_VERSION: Version = Version::new(0, 3, 0);
+
 //--------------------------------------------------------------------------------------------------
 // Type Definitions
 //--------------------------------------------------------------------------------------------------
@@ -48,13 +59,24 @@ pub struct PrivateDirectory {
     pub entries: BTreeMap<String, PrivateRef>,
 }
 
+/// A single revision of a private directory, decrypted with a snapshot ref. Its entries are
+/// snapshot refs to the revisions of its children at the time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrivateDirectorySnapshot {
+    pub version: Version,
+    pub metadata: Metadata,
+    pub entries: BTreeMap<String, SnapshotRef>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct PrivateDirectorySerde {
     pub r#type: NodeType,
     pub version: Version,
+    #[serde(default, skip_serializing_if = "CipherSuite::is_default")]
+    pub cipher_suite: CipherSuite,
     pub header: Vec<u8>,
     pub metadata: Metadata,
-    pub entries: BTreeMap<String, PrivateRef>,
+    pub entries: BTreeMap<String, PrivateRefSerde>,
 }
 
 /// The result of an operation applied to a directory.
@@ -93,13 +115,75 @@ impl PrivateDirectory {
     /// ```
     pub fn new<R: RngCore>(parent_bare_name: Namefilter, time: DateTime<Utc>, rng: &mut R) -> Self {
         Self {
-            version: Version::new(0, 2, 0),
+            version: PRIVATE_DIRECTORY_VERSION,
             header: PrivateNodeHeader::new(parent_bare_name, rng),
             metadata: Metadata::new(time),
             entries: BTreeMap::new(),
         }
     }
 
+    /// Creates a new directory that is encrypted with the given cipher suite. Files and directories
+    /// created inside of it are encrypted with the same cipher suite.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{private::CipherSuite, PrivateDirectory, Namefilter};
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// let rng = &mut thread_rng();
+    /// let dir = PrivateDirectory::with_cipher_suite(
+    ///     Namefilter::default(),
+    ///     CipherSuite::XChaCha20Poly1305,
+    ///     Utc::now(),
+    ///     rng,
+    /// );
+    ///
+    /// assert_eq!(dir.header.get_cipher_suite(), CipherSuite::XChaCha20Poly1305);
+    /// ```
+    pub fn with_cipher_suite<R: RngCore>(
+        parent_bare_name: Namefilter,
+        cipher_suite: CipherSuite,
+        time: DateTime<Utc>,
+        rng: &mut R,
+    ) -> Self {
+        let mut dir = Self::new(parent_bare_name, time, rng);
+        dir.header.cipher_suite = cipher_suite;
+        dir
+    }
+
+    /// Creates a new directory with the inumber and ratchet derived from a 32-byte seed instead of
+    /// drawn from a random number generator.
+    ///
+    /// Directories created from the same seed and parent bare name have the same private ref, so a
+    /// root directory can be recomputed from a seed alone, e.g. one derived from a recovery phrase.
+    /// The seed has to be kept secret, as it gives access to the directory and all its revisions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::Utc;
+    /// use wnfs::{Namefilter, PrivateDirectory};
+    ///
+    /// let seed = [7; 32];
+    /// let dir = PrivateDirectory::with_seed(Namefilter::default(), seed, Utc::now());
+    /// let recomputed = PrivateDirectory::with_seed(Namefilter::default(), seed, Utc::now());
+    ///
+    /// assert_eq!(
+    ///     dir.header.get_private_ref().unwrap(),
+    ///     recomputed.header.get_private_ref().unwrap()
+    /// );
+    /// ```
+    pub fn with_seed(parent_bare_name: Namefilter, seed: HashOutput, time: DateTime<Utc>) -> Self {
+        Self {
+            version: PRIVATE_DIRECTORY_VERSION,
+            header: PrivateNodeHeader::with_seed(parent_bare_name, &seed),
+            metadata: Metadata::new(time),
+            entries: BTreeMap::new(),
+        }
+    }
+
     /// Gets the metadata of the directory
     ///
     /// # Examples
@@ -135,6 +219,7 @@ impl PrivateDirectory {
         path_segments: &[String],
         time: DateTime<Utc>,
         parent_bare_name: Namefilter,
+        cipher_suite: CipherSuite,
         rng: &mut R,
     ) -> PrivatePathNodes {
         let mut working_parent_bare_name = parent_bare_name;
@@ -142,8 +227,9 @@ impl PrivateDirectory {
             .iter()
             .map(|segment| {
                 // Create new private directory.
-                let directory = Rc::new(PrivateDirectory::new(
+                let directory = Rc::new(PrivateDirectory::with_cipher_suite(
                     std::mem::take(&mut working_parent_bare_name),
+                    cipher_suite,
                     time,
                     rng,
                 ));
@@ -156,8 +242,9 @@ impl PrivateDirectory {
 
         PrivatePathNodes {
             path,
-            tail: Rc::new(PrivateDirectory::new(
+            tail: Rc::new(PrivateDirectory::with_cipher_suite(
                 std::mem::take(&mut working_parent_bare_name),
+                cipher_suite,
                 time,
                 rng,
             )),
@@ -236,9 +323,14 @@ impl PrivateDirectory {
                 // Get tail bare name from `path_so_far`.
                 let parent_bare_name = path_so_far.tail.header.bare_name.clone();
 
-                // Create missing directories.
-                let missing_path_nodes =
-                    Self::create_path_nodes(missing_path, time, parent_bare_name, rng);
+                // Create missing directories with the cipher suite of their parent.
+                let missing_path_nodes = Self::create_path_nodes(
+                    missing_path,
+                    time,
+                    parent_bare_name,
+                    path_so_far.tail.header.cipher_suite,
+                    rng,
+                );
 
                 Ok(PrivatePathNodes {
                     path: [
@@ -442,23 +534,194 @@ impl PrivateDirectory {
         store: &B,
     ) -> Result<PrivateOpResult<Vec<u8>>> {
         let root_dir = Rc::clone(&self);
+        let file = self
+            .get_file(path_segments, search_latest, &hamt, store)
+            .await?;
+
+        let result = file.get_content(&hamt, store).await?;
+
+        Ok(PrivateOpResult {
+            root_dir,
+            hamt,
+            result,
+        })
+    }
+
+    /// Reads the part of the specified file's content that falls within the given byte range. Only
+    /// the blocks covering the range are fetched and decrypted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateRef},
+    ///     BlockStore, MemoryBlockStore, Namefilter, PrivateDirectory, PrivateOpResult,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let hamt = Rc::new(PrivateForest::new());
+    ///
+    ///     let dir = Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     let PrivateOpResult { hamt, root_dir, .. } = dir
+    ///         .write(
+    ///             &["code".into(), "hello.py".into()],
+    ///             true,
+    ///             Utc::now(),
+    ///             b"print('hello world')".to_vec(),
+    ///             hamt,
+    ///             store,
+    ///             rng
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let PrivateOpResult { result, .. } = root_dir
+    ///         .read_range(&["code".into(), "hello.py".into()], true, 7, 11, hamt, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(&result, b"hello world");
+    /// }
+    /// ```
+    pub async fn read_range<B: BlockStore>(
+        self: Rc<Self>,
+        path_segments: &[String],
+        search_latest: bool,
+        offset: usize,
+        len: usize,
+        hamt: Rc<PrivateForest>,
+        store: &B,
+    ) -> Result<PrivateOpResult<Vec<u8>>> {
+        let root_dir = Rc::clone(&self);
+        let file = self
+            .get_file(path_segments, search_latest, &hamt, store)
+            .await?;
+
+        let result = file.get_content_range(offset, len, &hamt, store).await?;
+
+        Ok(PrivateOpResult {
+            root_dir,
+            hamt,
+            result,
+        })
+    }
+
+    /// Reads specified file content from the directory as a stream of decrypted blocks.
+    ///
+    /// The blocks are only fetched and decrypted as the stream is polled, so the file never has to
+    /// be held in memory in its entirety.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use futures::TryStreamExt;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateRef},
+    ///     BlockStore, MemoryBlockStore, Namefilter, PrivateDirectory, PrivateOpResult,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let hamt = Rc::new(PrivateForest::new());
+    ///
+    ///     let dir = Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     let content = b"print('hello world')";
+    ///
+    ///     let PrivateOpResult { hamt, root_dir, .. } = dir
+    ///         .write(
+    ///             &["code".into(), "hello.py".into()],
+    ///             true,
+    ///             Utc::now(),
+    ///             content.to_vec(),
+    ///             hamt,
+    ///             store,
+    ///             rng
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let PrivateOpResult { result, .. } = root_dir
+    ///         .read_stream(&["code".into(), "hello.py".into()], true, hamt, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let result: Vec<u8> = result.try_concat().await.unwrap();
+    ///
+    ///     assert_eq!(&result, content);
+    /// }
+    /// ```
+    pub async fn read_stream<'a, B: BlockStore>(
+        self: Rc<Self>,
+        path_segments: &[String],
+        search_latest: bool,
+        hamt: Rc<PrivateForest>,
+        store: &'a B,
+    ) -> Result<PrivateOpResult<LocalBoxStream<'a, Result<Vec<u8>>>>> {
+        let root_dir = Rc::clone(&self);
+        let file = self
+            .get_file(path_segments, search_latest, &hamt, store)
+            .await?;
+
+        let forest = Rc::clone(&hamt);
+        let result = Box::pin(try_stream! {
+            for await block in file.stream_content(0, &forest, store) {
+                yield block?;
+            }
+        });
+
+        Ok(PrivateOpResult {
+            root_dir,
+            hamt,
+            result,
+        })
+    }
+
+    /// Follows a path and fetches the file at the end of the path.
+    async fn get_file<B: BlockStore>(
+        self: Rc<Self>,
+        path_segments: &[String],
+        search_latest: bool,
+        hamt: &PrivateForest,
+        store: &B,
+    ) -> Result<Rc<PrivateFile>> {
         let (path, filename) = utils::split_last(path_segments)?;
 
         match self
-            .get_path_nodes(path, search_latest, &hamt, store)
+            .get_path_nodes(path, search_latest, hamt, store)
             .await?
         {
             PathNodesResult::Complete(node_path) => {
                 match node_path
                     .tail
-                    .lookup_node(filename, search_latest, &hamt, store)
+                    .lookup_node(filename, search_latest, hamt, store)
                     .await?
                 {
-                    Some(PrivateNode::File(file)) => Ok(PrivateOpResult {
-                        root_dir,
-                        hamt,
-                        result: file.content.clone(),
-                    }),
+                    Some(PrivateNode::File(file)) => Ok(file),
                     Some(PrivateNode::Dir(_)) => error(FsError::NotAFile),
                     None => error(FsError::NotFound),
                 }
@@ -527,6 +790,86 @@ impl PrivateDirectory {
         hamt: Rc<PrivateForest>,
         store: &mut B,
         rng: &mut R,
+    ) -> Result<PrivateOpResult<()>> {
+        self.write_stream(
+            path_segments,
+            search_latest,
+            time,
+            stream::once(future::ok(content)),
+            hamt,
+            store,
+            rng,
+        )
+        .await
+    }
+
+    /// Writes a file to the directory, taking its content from a stream of chunks.
+    ///
+    /// Chunks are encrypted and stored as soon as there is enough content to fill a block, so the
+    /// content never has to be held in memory in its entirety.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use futures::stream;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateRef},
+    ///     BlockStore, MemoryBlockStore, Namefilter, PrivateDirectory, PrivateOpResult,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let hamt = Rc::new(PrivateForest::new());
+    ///
+    ///     let dir = Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     let chunks = stream::iter(vec![
+    ///         Ok(b"print(".to_vec()),
+    ///         Ok(b"'hello world')".to_vec()),
+    ///     ]);
+    ///
+    ///     let PrivateOpResult { hamt, root_dir, .. } = dir
+    ///         .write_stream(
+    ///             &["code".into(), "hello.py".into()],
+    ///             true,
+    ///             Utc::now(),
+    ///             chunks,
+    ///             hamt,
+    ///             store,
+    ///             rng
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let PrivateOpResult { result, .. } = root_dir
+    ///         .read(&["code".into(), "hello.py".into()], true, hamt, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(&result, b"print('hello world')");
+    /// }
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub async fn write_stream<B: BlockStore, R: RngCore>(
+        self: Rc<Self>,
+        path_segments: &[String],
+        search_latest: bool,
+        time: DateTime<Utc>,
+        content: impl Stream<Item = Result<Vec<u8>>>,
+        hamt: Rc<PrivateForest>,
+        store: &mut B,
+        rng: &mut R,
     ) -> Result<PrivateOpResult<()>> {
         let (directory_path, filename) = utils::split_last(path_segments)?;
 
@@ -538,19 +881,27 @@ impl PrivateDirectory {
         let mut directory = (*directory_path_nodes.tail).clone();
 
         // Modify the file if it already exists, otherwise create a new file with expected content
-        let file = match directory
+        let (file, hamt) = match directory
             .lookup_node(filename, search_latest, &hamt, store)
             .await?
         {
             Some(PrivateNode::File(file_before)) => {
                 let mut file = (*file_before).clone();
-                file.content = content;
-                file.metadata.upsert_mtime(time);
+                let hamt = file
+                    .set_content_stream(time, content, hamt, store, rng)
+                    .await?;
                 file.header.advance_ratchet();
-                file
+                (file, hamt)
             }
             Some(PrivateNode::Dir(_)) => bail!(FsError::DirectoryAlreadyExists),
-            None => PrivateFile::new(directory.header.bare_name.clone(), time, content, rng),
+            None => {
+                let mut file = PrivateFile::new(directory.header.bare_name.clone(), time, rng);
+                file.header.cipher_suite = directory.header.cipher_suite;
+                let hamt = file
+                    .set_content_stream(time, content, hamt, store, rng)
+                    .await?;
+                (file, hamt)
+            }
         };
 
         let child_private_ref = file.header.get_private_ref()?;
@@ -1129,6 +1480,110 @@ impl PrivateDirectory {
             .await
     }
 
+    /// Resets the ratchets of the file or directory at the path and everything under it, so they
+    /// are stored under fresh keys from then on. Holders of an earlier private ref to any of the
+    /// nodes can still read the revisions they had access to, but not the ones that follow. This
+    /// is how access to a subtree is revoked.
+    ///
+    /// An empty path rotates the keys of the whole tree. The new private ref of the node at the
+    /// path is returned, so it can be shared again with the remaining collaborators.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{
+    ///     private::PrivateForest, MemoryBlockStore, Namefilter, PrivateDirectory, PrivateOpResult,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let hamt = Rc::new(PrivateForest::new());
+    ///     let dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+    ///
+    ///     let PrivateOpResult { root_dir, hamt, .. } = dir
+    ///         .mkdir(&["shared".into()], true, Utc::now(), hamt, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let PrivateOpResult { result, .. } = Rc::clone(&root_dir)
+    ///         .get_node(&["shared".into()], true, Rc::clone(&hamt), store)
+    ///         .await
+    ///         .unwrap();
+    ///     let old_ref = result.unwrap().get_header().get_private_ref().unwrap();
+    ///
+    ///     let PrivateOpResult { result: new_ref, .. } = root_dir
+    ///         .rotate_keys(&["shared".into()], true, hamt, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_ne!(new_ref, old_ref);
+    /// }
+    /// ```
+    pub async fn rotate_keys<B: BlockStore, R: RngCore>(
+        self: Rc<Self>,
+        path_segments: &[String],
+        search_latest: bool,
+        hamt: Rc<PrivateForest>,
+        store: &mut B,
+        rng: &mut R,
+    ) -> Result<PrivateOpResult<PrivateRef>> {
+        let (directory_path, node_name) = match path_segments.split_last() {
+            Some((node_name, directory_path)) => (directory_path, node_name),
+            None => {
+                let mut node = PrivateNode::Dir(self);
+                let hamt = node.rotate_keys(hamt, store, rng).await?;
+
+                return Ok(PrivateOpResult {
+                    result: node.get_header().get_private_ref()?,
+                    root_dir: node.as_dir()?,
+                    hamt,
+                });
+            }
+        };
+
+        let mut path_nodes = match self
+            .get_path_nodes(directory_path, search_latest, &hamt, store)
+            .await?
+        {
+            PrivatePathNodesResult::Complete(node_path) => node_path,
+            _ => bail!(FsError::NotFound),
+        };
+
+        let mut directory = (*path_nodes.tail).clone();
+
+        let mut node = match directory.entries.get(node_name) {
+            Some(private_ref) => hamt
+                .get(private_ref, store)
+                .await?
+                .ok_or(FsError::NotFound)?,
+            None => bail!(FsError::NotFound),
+        };
+
+        let hamt = node.rotate_keys(hamt, store, rng).await?;
+        let private_ref = node.get_header().get_private_ref()?;
+
+        directory
+            .entries
+            .insert(node_name.clone(), private_ref.clone());
+
+        path_nodes.tail = Rc::new(directory);
+
+        let (root_dir, hamt) = Self::fix_up_path_nodes(path_nodes, hamt, store, rng).await?;
+
+        Ok(PrivateOpResult {
+            root_dir,
+            hamt,
+            result: private_ref,
+        })
+    }
+
     /// Serializes the directory with provided Serde serialilzer.
     pub(crate) fn serialize<S, R: RngCore>(
         &self,
@@ -1147,14 +1602,21 @@ impl PrivateDirectory {
         (PrivateDirectorySerde {
             r#type: NodeType::PrivateDirectory,
             version: self.version.clone(),
+            cipher_suite: self.header.cipher_suite,
             header: {
                 let cbor_bytes = dagcbor::encode(&self.header).map_err(SerError::custom)?;
-                key.0
-                    .encrypt(&Key::generate_nonce(rng), &cbor_bytes)
+                self.header
+                    .cipher_suite
+                    .encrypt(&key.0, &cbor_bytes, rng)
                     .map_err(SerError::custom)?
             },
             metadata: self.metadata.clone(),
-            entries: self.entries.clone(),
+            entries: self
+                .entries
+                .iter()
+                .map(|(name, private_ref)| Ok((name.clone(), private_ref.to_serde(&key, rng)?)))
+                .collect::<Result<_>>()
+                .map_err(SerError::custom)?,
         })
         .serialize(serializer)
     }
@@ -1167,19 +1629,68 @@ impl PrivateDirectory {
         let PrivateDirectorySerde {
             version,
             metadata,
+            cipher_suite,
             header,
             entries,
             ..
         } = PrivateDirectorySerde::deserialize(deserializer)?;
 
+        // Entries of older directories are upgraded when read, so the directory is written back
+        // in the current format.
+        let (version, entries) = if version < PRIVATE_DIRECTORY_VERSION {
+            let entries = entries
+                .into_iter()
+                .map(|(name, private_ref)| Ok((name, PrivateRef::from_legacy_serde(private_ref)?)))
+                .collect::<Result<_>>();
+
+            (PRIVATE_DIRECTORY_VERSION, entries)
+        } else {
+            let entries = entries
+                .into_iter()
+                .map(|(name, private_ref)| Ok((name, PrivateRef::from_serde(private_ref, key)?)))
+                .collect::<Result<_>>();
+
+            (version, entries)
+        };
+
         Ok(Self {
             version,
             metadata,
             header: {
-                let cbor_bytes = key.0.decrypt(&header).map_err(DeError::custom)?;
-                dagcbor::decode(&cbor_bytes).map_err(DeError::custom)?
+                let cbor_bytes = cipher_suite
+                    .decrypt(&key.0, &header)
+                    .map_err(DeError::custom)?;
+                PrivateNodeHeader {
+                    cipher_suite,
+                    ..dagcbor::decode(&cbor_bytes).map_err(DeError::custom)?
+                }
             },
+            entries: entries.map_err(DeError::custom)?,
+        })
+    }
+}
+
+impl PrivateDirectorySnapshot {
+    /// Deserializes the directory revision with provided Serde deserializer, leaving out the
+    /// header and the ratchet keys of the entries.
+    pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let PrivateDirectorySerde {
+            version,
+            metadata,
             entries,
+            ..
+        } = PrivateDirectorySerde::deserialize(deserializer)?;
+
+        Ok(Self {
+            version,
+            metadata,
+            entries: entries
+                .into_iter()
+                .map(|(name, private_ref)| (name, private_ref.into()))
+                .collect(),
         })
     }
 }
@@ -1197,11 +1708,130 @@ impl Id for PrivateDirectory {
 #[cfg(test)]
 mod private_directory_tests {
     use super::*;
-    use crate::MemoryBlockStore;
+    use crate::{
+        private::{FileContent, MAX_BLOCK_CONTENT_SIZE},
+        MemoryBlockStore,
+    };
+    use futures::TryStreamExt;
     use proptest::test_runner::{RngAlgorithm, TestRng};
 
     use test_log::test;
 
+    #[test(async_std::test)]
+    async fn seeded_root_dir_can_be_recomputed_to_find_latest_revision() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &mut MemoryBlockStore::default();
+        let seed = utils::get_random_bytes(rng);
+        let root_dir = Rc::new(PrivateDirectory::with_seed(
+            Namefilter::default(),
+            seed,
+            Utc::now(),
+        ));
+
+        // Store the first revision, so later revisions can be found from the seed.
+        let hamt = Rc::new(PrivateForest::new())
+            .set(
+                root_dir.header.get_saturated_name(),
+                &root_dir.header.get_private_ref().unwrap(),
+                &PrivateNode::Dir(Rc::clone(&root_dir)),
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        let PrivateOpResult { hamt, .. } = Rc::clone(&root_dir)
+            .mkdir(&["pictures".into()], true, Utc::now(), hamt, store, rng)
+            .await
+            .unwrap();
+
+        let recomputed = PrivateNode::from(PrivateDirectory::with_seed(
+            Namefilter::default(),
+            seed,
+            Utc::now(),
+        ));
+        let latest = recomputed.search_latest(&hamt, store).await.unwrap();
+
+        let PrivateOpResult { result, .. } = latest
+            .as_dir()
+            .unwrap()
+            .ls(&[], true, hamt, store)
+            .await
+            .unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].0, "pictures");
+
+        let other = PrivateDirectory::with_seed(Namefilter::default(), [0; 32], Utc::now());
+
+        assert_ne!(
+            other.header.get_private_ref().unwrap(),
+            root_dir.header.get_private_ref().unwrap()
+        );
+    }
+
+    #[test(async_std::test)]
+    async fn cipher_suite_is_inherited_and_used_for_reading() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &mut MemoryBlockStore::default();
+        let root_dir = Rc::new(PrivateDirectory::with_cipher_suite(
+            Namefilter::default(),
+            CipherSuite::XChaCha20Poly1305,
+            Utc::now(),
+            rng,
+        ));
+        let path = ["docs".into(), "large.bin".into()];
+        let content = vec![3u8; MAX_BLOCK_CONTENT_SIZE * 2];
+
+        let PrivateOpResult { root_dir, hamt, .. } = root_dir
+            .write(
+                &path,
+                true,
+                Utc::now(),
+                content.clone(),
+                Rc::new(PrivateForest::new()),
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        let PrivateOpResult { result, hamt, .. } = Rc::clone(&root_dir)
+            .get_node(&path, true, hamt, store)
+            .await
+            .unwrap();
+        let file = result.unwrap();
+
+        assert_eq!(
+            file.get_header().get_cipher_suite(),
+            CipherSuite::XChaCha20Poly1305
+        );
+
+        let private_ref = root_dir.header.get_private_ref().unwrap();
+        let node = hamt.get(&private_ref, store).await.unwrap().unwrap();
+
+        assert_eq!(node.as_dir().unwrap(), root_dir);
+
+        let file = file.as_file().unwrap();
+
+        assert_eq!(file.get_content(&hamt, store).await.unwrap(), content);
+        assert!(matches!(
+            file.content,
+            FileContent::External {
+                cipher_suite: CipherSuite::XChaCha20Poly1305,
+                block_count: 3,
+                ..
+            }
+        ));
+
+        let aes_ref = PrivateRef {
+            cipher_suite: CipherSuite::Aes256Gcm,
+            ..private_ref
+        };
+
+        assert!(hamt.get(&aes_ref, store).await.is_err());
+    }
+
     #[test(async_std::test)]
     async fn look_up_can_fetch_file_added_to_directory() {
         let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
@@ -1448,6 +2078,50 @@ mod private_directory_tests {
         assert_eq!(result, b"text".to_vec());
     }
 
+    #[test(async_std::test)]
+    async fn write_stream_and_read_stream_handle_content_spanning_multiple_blocks() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let store = &mut MemoryBlockStore::default();
+        let hamt = Rc::new(PrivateForest::new());
+
+        let mut content = vec![0u8; MAX_BLOCK_CONTENT_SIZE * 2 + 1000];
+        rng.fill_bytes(&mut content);
+
+        // Chunk sizes deliberately don't line up with block boundaries.
+        let chunks = content
+            .chunks(100_000)
+            .map(|chunk| Ok(chunk.to_vec()))
+            .collect::<Vec<_>>();
+
+        let PrivateOpResult { root_dir, hamt, .. } = root_dir
+            .write_stream(
+                &["videos".into(), "movie.mp4".into()],
+                true,
+                Utc::now(),
+                stream::iter(chunks),
+                hamt,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        let PrivateOpResult { result, .. } = root_dir
+            .read_stream(&["videos".into(), "movie.mp4".into()], true, hamt, store)
+            .await
+            .unwrap();
+
+        let blocks = result.try_collect::<Vec<_>>().await.unwrap();
+
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks.concat(), content);
+    }
+
     #[async_std::test]
     async fn path_nodes_can_generates_new_path_nodes() {
         let store = &mut MemoryBlockStore::default();
@@ -1458,6 +2132,7 @@ mod private_directory_tests {
             &["Documents".into(), "Apps".into()],
             Utc::now(),
             Namefilter::default(),
+            CipherSuite::default(),
             rng,
         );
 
@@ -1907,4 +2582,122 @@ mod private_directory_tests {
 
         assert!(result.is_err());
     }
+
+    #[test(async_std::test)]
+    async fn rotate_keys_revokes_access_to_later_revisions() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let store = &mut MemoryBlockStore::default();
+        let hamt = Rc::new(PrivateForest::new());
+        let root_dir = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let path = ["shared".to_string(), "notes.txt".to_string()];
+
+        let PrivateOpResult { root_dir, hamt, .. } = root_dir
+            .write(&path, true, Utc::now(), b"draft".to_vec(), hamt, store, rng)
+            .await
+            .unwrap();
+
+        let old_node = root_dir
+            .lookup_node("shared", true, &hamt, store)
+            .await
+            .unwrap()
+            .unwrap();
+        let old_ref = old_node.get_header().get_private_ref().unwrap();
+
+        let PrivateOpResult {
+            root_dir,
+            hamt,
+            result: new_ref,
+        } = root_dir
+            .rotate_keys(&["shared".into()], true, hamt, store, rng)
+            .await
+            .unwrap();
+
+        let PrivateOpResult { root_dir, hamt, .. } = root_dir
+            .write(&path, true, Utc::now(), b"final".to_vec(), hamt, store, rng)
+            .await
+            .unwrap();
+
+        // The old private ref only leads to the revisions from before the rotation.
+        let old_latest = hamt
+            .get(&old_ref, store)
+            .await
+            .unwrap()
+            .unwrap()
+            .search_latest(&hamt, store)
+            .await
+            .unwrap();
+
+        assert_eq!(old_latest, old_node);
+
+        // The new private ref leads to the latest revision.
+        let new_latest = hamt
+            .get(&new_ref, store)
+            .await
+            .unwrap()
+            .unwrap()
+            .search_latest(&hamt, store)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            Some(new_latest),
+            root_dir
+                .lookup_node("shared", true, &hamt, store)
+                .await
+                .unwrap()
+        );
+
+        let PrivateOpResult { result, .. } = root_dir.read(&path, true, hamt, store).await.unwrap();
+
+        assert_eq!(result, b"final".to_vec());
+    }
+
+    #[test(async_std::test)]
+    async fn directories_with_unencrypted_entry_keys_can_be_read() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let file = PrivateFile::new(Namefilter::default(), Utc::now(), rng);
+        let file_ref = file.header.get_private_ref().unwrap();
+        let dir = PrivateDirectory::new(Namefilter::default(), Utc::now(), rng);
+        let key = dir.header.get_private_ref().unwrap().ratchet_key;
+
+        // The form directories were encoded in at version 0.2.0, with plain private refs as entries.
+        #[derive(Serialize)]
+        struct LegacyPrivateDirectorySerde {
+            r#type: NodeType,
+            version: Version,
+            header: Vec<u8>,
+            metadata: Metadata,
+            entries: BTreeMap<String, PrivateRef>,
+        }
+
+        let legacy = LegacyPrivateDirectorySerde {
+            r#type: NodeType::PrivateDirectory,
+            version: Version::new(0, 2, 0),
+            header: {
+                let cbor_bytes = dagcbor::encode(&dir.header).unwrap();
+                CipherSuite::default()
+                    .encrypt(&key.0, &cbor_bytes, rng)
+                    .unwrap()
+            },
+            metadata: dir.metadata.clone(),
+            entries: BTreeMap::from([("text.txt".into(), file_ref.clone())]),
+        };
+        let ipld = libipld::serde::to_ipld(&legacy).unwrap();
+
+        let decoded = PrivateDirectory::deserialize(ipld, &key).unwrap();
+
+        assert_eq!(decoded.version, PRIVATE_DIRECTORY_VERSION);
+        assert_eq!(decoded.header, dir.header);
+        assert_eq!(decoded.entries.get("text.txt"), Some(&file_ref));
+
+        // Once read, the directory is written back in the current format.
+        let ipld = decoded.serialize(libipld::serde::Serializer, rng).unwrap();
+        let reread = PrivateDirectory::deserialize(ipld, &key).unwrap();
+
+        assert_eq!(reread.entries, decoded.entries);
+    }
 }
@@ -1,11 +1,36 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+use async_stream::try_stream;
 use chrono::{DateTime, Utc};
+use futures::{future, pin_mut, stream, Stream, StreamExt, TryStreamExt};
+use libipld::{serde as ipld_serde, Ipld, IpldCodec};
 use rand_core::RngCore;
 use semver::Version;
 use serde::{de::Error as DeError, ser::Error as SerError, Deserialize, Deserializer, Serialize};
+use sha3::Sha3_256;
+
+use crate::{dagcbor, utils, BlockStore, FsError, Id, Metadata, NodeType};
+
+use super::{
+    hamt::Hasher, namefilter::Namefilter, CipherSuite, Key, PrivateForest, PrivateNodeHeader,
+    RatchetKey, AUTHENTICATION_TAG_SIZE, NONCE_SIZE,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
 
-use crate::{dagcbor, Id, Metadata, NodeType};
+/// The maximum size of an encrypted content block in bytes.
+pub const MAX_BLOCK_SIZE: usize = usize::pow(2, 18);
 
-use super::{namefilter::Namefilter, Key, PrivateNodeHeader, RatchetKey};
+/// The maximum number of plaintext bytes stored in a single content block with the default cipher
+/// suite. This is the block size minus the nonce and authentication tag added by encryption.
+pub const MAX_BLOCK_CONTENT_SIZE: usize = MAX_BLOCK_SIZE - NONCE_SIZE - AUTHENTICATION_TAG_SIZE;
+
+/// The version of the private file format. Files from before 0.3.0 store their content as plain
+/// bytes in the file node.
+const PRIVATE_FILE_VERSION: Version = Version::new(0, 3, 0);
 
 //--------------------------------------------------------------------------------------------------
 // Type Definitions
@@ -24,7 +49,6 @@ use super::{namefilter::Namefilter, Key, PrivateNodeHeader, RatchetKey};
 /// let file = PrivateFile::new(
 ///     Namefilter::default(),
 ///     Utc::now(),
-///     b"hello world".to_vec(),
 ///     rng,
 /// );
 ///
@@ -35,16 +59,53 @@ pub struct PrivateFile {
     pub version: Version,
     pub header: PrivateNodeHeader,
     pub metadata: Metadata,
-    pub content: Vec<u8>, // TODO(appcypher): Support linked file content.
+    pub content: FileContent,
+}
+
+/// The content of a private file.
+///
+/// New files start out with empty inline content. Content written to a file is always split into
+/// encrypted blocks that are stored in the private forest, and the file node only keeps the
+/// information needed to find and decrypt them.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileContent {
+    Inline {
+        data: Vec<u8>,
+    },
+    External {
+        /// The key used to encrypt the content blocks.
+        key: Key,
+        /// The number of content blocks.
+        block_count: usize,
+        /// The maximum number of plaintext bytes in each block.
+        block_content_size: usize,
+        /// The namefilter the block labels are derived from.
+        base_name: Namefilter,
+        /// The cipher suite the content blocks are encrypted with.
+        #[serde(default, skip_serializing_if = "CipherSuite::is_default")]
+        cipher_suite: CipherSuite,
+    },
+}
+
+/// A single revision of a private file, decrypted with a snapshot ref. Its content can be read, but
+/// it has no header to derive later revisions from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrivateFileSnapshot {
+    pub version: Version,
+    pub metadata: Metadata,
+    pub content: FileContent,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct PrivateFileSerde {
     pub r#type: NodeType,
     pub version: Version,
+    #[serde(default, skip_serializing_if = "CipherSuite::is_default")]
+    pub cipher_suite: CipherSuite,
     pub header: Vec<u8>,
     pub metadata: Metadata,
-    pub content: Vec<u8>,
+    pub content: Ipld,
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -52,7 +113,7 @@ struct PrivateFileSerde {
 //--------------------------------------------------------------------------------------------------
 
 impl PrivateFile {
-    /// Creates a new file using the given metadata and CID.
+    /// Creates an empty file.
     ///
     /// # Examples
     ///
@@ -65,24 +126,443 @@ impl PrivateFile {
     /// let file = PrivateFile::new(
     ///     Namefilter::default(),
     ///     Utc::now(),
-    ///     b"hello world".to_vec(),
     ///     rng,
     /// );
     ///
     /// println!("file = {:?}", file);
     /// ```
-    pub fn new<R: RngCore>(
+    pub fn new<R: RngCore>(parent_bare_name: Namefilter, time: DateTime<Utc>, rng: &mut R) -> Self {
+        Self {
+            version: PRIVATE_FILE_VERSION,
+            header: PrivateNodeHeader::new(parent_bare_name, rng),
+            metadata: Metadata::new(time),
+            content: FileContent::Inline { data: vec![] },
+        }
+    }
+
+    /// Creates a file with provided content.
+    ///
+    /// The content is split into encrypted blocks that are stored in the private forest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{private::PrivateForest, MemoryBlockStore, Namefilter, PrivateFile};
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let hamt = Rc::new(PrivateForest::new());
+    ///
+    ///     let (file, hamt) = PrivateFile::with_content(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         b"hello world".to_vec(),
+    ///         hamt,
+    ///         store,
+    ///         rng,
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    ///
+    ///     let content = file.get_content(&hamt, store).await.unwrap();
+    ///
+    ///     assert_eq!(&content, b"hello world");
+    /// }
+    /// ```
+    pub async fn with_content<B: BlockStore, R: RngCore>(
         parent_bare_name: Namefilter,
         time: DateTime<Utc>,
         content: Vec<u8>,
+        hamt: Rc<PrivateForest>,
+        store: &mut B,
         rng: &mut R,
-    ) -> Self {
-        Self {
-            version: Version::new(0, 2, 0),
-            header: PrivateNodeHeader::new(parent_bare_name, rng),
-            metadata: Metadata::new(time),
+    ) -> Result<(Self, Rc<PrivateForest>)> {
+        let header = PrivateNodeHeader::new(parent_bare_name, rng);
+        let (content, hamt) = Self::prepare_content(
+            &header.bare_name,
+            header.cipher_suite,
+            stream::once(future::ok(content)),
+            hamt,
+            store,
+            rng,
+        )
+        .await?;
+
+        Ok((
+            Self {
+                version: PRIVATE_FILE_VERSION,
+                header,
+                metadata: Metadata::new(time),
+                content,
+            },
+            hamt,
+        ))
+    }
+
+    /// Replaces the content of the file and updates its modified time.
+    ///
+    /// This does not advance the ratchet of the file.
+    pub async fn set_content<B: BlockStore, R: RngCore>(
+        &mut self,
+        time: DateTime<Utc>,
+        content: Vec<u8>,
+        hamt: Rc<PrivateForest>,
+        store: &mut B,
+        rng: &mut R,
+    ) -> Result<Rc<PrivateForest>> {
+        self.set_content_stream(time, stream::once(future::ok(content)), hamt, store, rng)
+            .await
+    }
+
+    /// Replaces the content of the file with the chunks of the provided stream and updates its
+    /// modified time. Chunks are buffered until there is enough content to fill a block, so chunks
+    /// can have any size.
+    ///
+    /// This does not advance the ratchet of the file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use futures::stream;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{private::PrivateForest, MemoryBlockStore, Namefilter, PrivateFile};
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let hamt = Rc::new(PrivateForest::new());
+    ///
+    ///     let mut file = PrivateFile::new(Namefilter::default(), Utc::now(), rng);
+    ///     let chunks = stream::iter(vec![Ok(b"hello ".to_vec()), Ok(b"world".to_vec())]);
+    ///
+    ///     let hamt = file
+    ///         .set_content_stream(Utc::now(), chunks, hamt, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let content = file.get_content(&hamt, store).await.unwrap();
+    ///
+    ///     assert_eq!(&content, b"hello world");
+    /// }
+    /// ```
+    pub async fn set_content_stream<B: BlockStore, R: RngCore>(
+        &mut self,
+        time: DateTime<Utc>,
+        content: impl Stream<Item = Result<Vec<u8>>>,
+        hamt: Rc<PrivateForest>,
+        store: &mut B,
+        rng: &mut R,
+    ) -> Result<Rc<PrivateForest>> {
+        let (content, hamt) = Self::prepare_content(
+            &self.header.bare_name,
+            self.header.cipher_suite,
             content,
+            hamt,
+            store,
+            rng,
+        )
+        .await?;
+
+        self.content = content;
+        self.metadata.upsert_mtime(time);
+
+        Ok(hamt)
+    }
+
+    /// Gets the entire content of the file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{private::PrivateForest, MemoryBlockStore, Namefilter, PrivateFile};
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let hamt = Rc::new(PrivateForest::new());
+    ///
+    ///     let (file, hamt) = PrivateFile::with_content(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         b"hello world".to_vec(),
+    ///         hamt,
+    ///         store,
+    ///         rng,
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    ///
+    ///     let content = file.get_content(&hamt, store).await.unwrap();
+    ///
+    ///     assert_eq!(&content, b"hello world");
+    /// }
+    /// ```
+    pub async fn get_content<B: BlockStore>(
+        &self,
+        hamt: &PrivateForest,
+        store: &B,
+    ) -> Result<Vec<u8>> {
+        self.stream_content(0, hamt, store).try_concat().await
+    }
+
+    /// Gets the part of the file content that falls within the given byte range. Only the blocks
+    /// covering the range are fetched and decrypted. The range is clamped to the size of the file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{private::PrivateForest, MemoryBlockStore, Namefilter, PrivateFile};
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let hamt = Rc::new(PrivateForest::new());
+    ///
+    ///     let (file, hamt) = PrivateFile::with_content(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         b"hello world".to_vec(),
+    ///         hamt,
+    ///         store,
+    ///         rng,
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    ///
+    ///     let content = file.get_content_range(6, 5, &hamt, store).await.unwrap();
+    ///
+    ///     assert_eq!(&content, b"world");
+    /// }
+    /// ```
+    pub async fn get_content_range<B: BlockStore>(
+        &self,
+        offset: usize,
+        len: usize,
+        hamt: &PrivateForest,
+        store: &B,
+    ) -> Result<Vec<u8>> {
+        let block_content_size = match &self.content {
+            FileContent::Inline { data } => return Ok(utils::get_range(data, offset, len).to_vec()),
+            FileContent::External {
+                block_content_size, ..
+            } => *block_content_size,
+        };
+
+        if len == 0 {
+            return Ok(vec![]);
+        }
+
+        let end = offset.saturating_add(len);
+        let first_block = offset / block_content_size;
+        let last_block = (end - 1) / block_content_size;
+
+        let mut content = Vec::new();
+        let mut block_offset = first_block * block_content_size;
+        let blocks = self
+            .stream_content(first_block, hamt, store)
+            .take(last_block - first_block + 1);
+
+        pin_mut!(blocks);
+        while let Some(block) = blocks.next().await {
+            let block = block?;
+            content.extend_from_slice(utils::get_range(
+                &block,
+                offset.saturating_sub(block_offset),
+                end - offset.max(block_offset),
+            ));
+            block_offset += block.len();
         }
+
+        Ok(content)
+    }
+
+    /// Streams the content of the file one decrypted block at a time, starting at the given
+    /// block index.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use futures::StreamExt;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{private::PrivateForest, MemoryBlockStore, Namefilter, PrivateFile};
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let hamt = Rc::new(PrivateForest::new());
+    ///
+    ///     let (file, hamt) = PrivateFile::with_content(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         b"hello world".to_vec(),
+    ///         hamt,
+    ///         store,
+    ///         rng,
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    ///
+    ///     let mut content = Vec::new();
+    ///     let mut stream = Box::pin(file.stream_content(0, &hamt, store));
+    ///     while let Some(block) = stream.next().await {
+    ///         content.extend(block.unwrap());
+    ///     }
+    ///
+    ///     assert_eq!(&content, b"hello world");
+    /// }
+    /// ```
+    pub fn stream_content<'a, B: BlockStore>(
+        &'a self,
+        block_index: usize,
+        hamt: &'a PrivateForest,
+        store: &'a B,
+    ) -> impl Stream<Item = Result<Vec<u8>>> + 'a {
+        self.content.stream(block_index, hamt, store)
+    }
+
+    /// Splits the content into encrypted blocks and stores them in the private forest.
+    async fn prepare_content<B: BlockStore, R: RngCore>(
+        bare_name: &Namefilter,
+        cipher_suite: CipherSuite,
+        content: impl Stream<Item = Result<Vec<u8>>>,
+        mut hamt: Rc<PrivateForest>,
+        store: &mut B,
+        rng: &mut R,
+    ) -> Result<(FileContent, Rc<PrivateForest>)> {
+        let key = Key::new(utils::get_random_bytes(rng));
+        let block_content_size =
+            MAX_BLOCK_SIZE - cipher_suite.nonce_size() - AUTHENTICATION_TAG_SIZE;
+        let mut block_count = 0;
+        let mut buffer = Vec::with_capacity(block_content_size);
+
+        pin_mut!(content);
+        while let Some(chunk) = content.next().await {
+            let chunk = chunk?;
+            let mut remaining = &chunk[..];
+            while !remaining.is_empty() {
+                let size = (block_content_size - buffer.len()).min(remaining.len());
+                buffer.extend_from_slice(&remaining[..size]);
+                remaining = &remaining[size..];
+
+                if buffer.len() == block_content_size {
+                    let enc_bytes = cipher_suite.encrypt(&key, &buffer, rng)?;
+                    hamt = Self::store_block(&key, block_count, bare_name, enc_bytes, hamt, store)
+                        .await?;
+                    block_count += 1;
+                    buffer.clear();
+                }
+            }
+        }
+
+        if !buffer.is_empty() {
+            let enc_bytes = cipher_suite.encrypt(&key, &buffer, rng)?;
+            hamt = Self::store_block(&key, block_count, bare_name, enc_bytes, hamt, store).await?;
+            block_count += 1;
+        }
+
+        Ok((
+            FileContent::External {
+                key,
+                block_count,
+                block_content_size,
+                base_name: bare_name.clone(),
+                cipher_suite,
+            },
+            hamt,
+        ))
+    }
+
+    /// Stores an encrypted content block in the block store and adds it to the private forest.
+    async fn store_block<B: BlockStore>(
+        key: &Key,
+        index: usize,
+        bare_name: &Namefilter,
+        enc_bytes: Vec<u8>,
+        hamt: Rc<PrivateForest>,
+        store: &mut B,
+    ) -> Result<Rc<PrivateForest>> {
+        let cid = store.put_block(enc_bytes, IpldCodec::Raw).await?;
+
+        let label = Self::create_block_label(key, index, bare_name);
+        hamt.set_encrypted(label, cid, store).await
+    }
+
+    /// Fetches a content block from the private forest and decrypts it.
+    async fn decrypt_block<B: BlockStore>(
+        key: &Key,
+        cipher_suite: CipherSuite,
+        index: usize,
+        bare_name: &Namefilter,
+        hamt: &PrivateForest,
+        store: &B,
+    ) -> Result<Vec<u8>> {
+        let label = Self::create_block_label(key, index, bare_name);
+        let label_hash = &Sha3_256::hash(&label.as_bytes());
+
+        let cids = hamt
+            .get_encrypted(label_hash, store)
+            .await?
+            .ok_or(FsError::FileShardNotFound)?;
+
+        // Blocks are only written once per label, but merged forests can hold several. Return the
+        // first one that decrypts with the content key.
+        let mut error = None;
+        for cid in cids {
+            let bytes = async {
+                let enc_bytes = store.get_block(cid).await?;
+                cipher_suite.decrypt(key, &enc_bytes)
+            };
+
+            match bytes.await {
+                Ok(bytes) => return Ok(bytes),
+                Err(err) => error = Some(err),
+            }
+        }
+
+        Err(error.unwrap_or_else(|| FsError::FileShardNotFound.into()))
+    }
+
+    /// Creates the label for a content block. The label is the saturated namefilter of the
+    /// base name, the content key and a hash of the key and block index.
+    fn create_block_label(key: &Key, index: usize, bare_name: &Namefilter) -> Namefilter {
+        let key_bytes = key.as_bytes();
+        let key_hash = Sha3_256::hash(&[key_bytes, &(index as u64).to_le_bytes()].concat());
+
+        let mut label = bare_name.clone();
+        label.add(&key_bytes);
+        label.add(&key_hash);
+        label.saturate();
+
+        label
     }
 
     /// Serializes the file with provided Serde serialilzer.
@@ -103,14 +583,16 @@ impl PrivateFile {
         (PrivateFileSerde {
             r#type: NodeType::PrivateFile,
             version: self.version.clone(),
+            cipher_suite: self.header.cipher_suite,
             header: {
                 let cbor_bytes = dagcbor::encode(&self.header).map_err(SerError::custom)?;
-                key.0
-                    .encrypt(&Key::generate_nonce(rng), &cbor_bytes)
+                self.header
+                    .cipher_suite
+                    .encrypt(&key.0, &cbor_bytes, rng)
                     .map_err(SerError::custom)?
             },
             metadata: self.metadata.clone(),
-            content: self.content.clone(),
+            content: ipld_serde::to_ipld(&self.content).map_err(SerError::custom)?,
         })
         .serialize(serializer)
     }
@@ -123,17 +605,27 @@ impl PrivateFile {
         let PrivateFileSerde {
             version,
             metadata,
+            cipher_suite,
             header,
             content,
             ..
         } = PrivateFileSerde::deserialize(deserializer)?;
 
+        // Content of older files is upgraded when read, so the file is written back in the current
+        // format.
+        let content = FileContent::from_serde(content, &version).map_err(DeError::custom)?;
+
         Ok(Self {
-            version,
+            version: version.max(PRIVATE_FILE_VERSION),
             metadata,
             header: {
-                let cbor_bytes = key.0.decrypt(&header).map_err(DeError::custom)?;
-                dagcbor::decode(&cbor_bytes).map_err(DeError::custom)?
+                let cbor_bytes = cipher_suite
+                    .decrypt(&key.0, &header)
+                    .map_err(DeError::custom)?;
+                PrivateNodeHeader {
+                    cipher_suite,
+                    ..dagcbor::decode(&cbor_bytes).map_err(DeError::custom)?
+                }
             },
             content,
         })
@@ -145,3 +637,308 @@ impl Id for PrivateFile {
         format!("{:p}", &self.header)
     }
 }
+
+impl PrivateFileSnapshot {
+    /// Gets the entire content of the file revision.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{
+    ///     private::PrivateForest, MemoryBlockStore, Namefilter, PrivateFile, PrivateNode,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let hamt = Rc::new(PrivateForest::new());
+    ///
+    ///     let (file, hamt) = PrivateFile::with_content(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         b"hello world".to_vec(),
+    ///         hamt,
+    ///         store,
+    ///         rng,
+    ///     )
+    ///     .await
+    ///     .unwrap();
+    ///
+    ///     let private_ref = &file.header.get_private_ref().unwrap();
+    ///     let name = file.header.get_saturated_name();
+    ///     let node = PrivateNode::File(Rc::new(file));
+    ///     let hamt = hamt.set(name, private_ref, &node, store, rng).await.unwrap();
+    ///
+    ///     let snapshot = hamt
+    ///         .get_snapshot(&private_ref.as_snapshot_ref(), store)
+    ///         .await
+    ///         .unwrap()
+    ///         .unwrap();
+    ///
+    ///     let content = snapshot.as_file().unwrap().get_content(&hamt, store).await.unwrap();
+    ///
+    ///     assert_eq!(&content, b"hello world");
+    /// }
+    /// ```
+    pub async fn get_content<B: BlockStore>(
+        &self,
+        hamt: &PrivateForest,
+        store: &B,
+    ) -> Result<Vec<u8>> {
+        self.stream_content(0, hamt, store).try_concat().await
+    }
+
+    /// Streams the content of the file revision one decrypted block at a time, starting at the
+    /// given block index.
+    pub fn stream_content<'a, B: BlockStore>(
+        &'a self,
+        block_index: usize,
+        hamt: &'a PrivateForest,
+        store: &'a B,
+    ) -> impl Stream<Item = Result<Vec<u8>>> + 'a {
+        self.content.stream(block_index, hamt, store)
+    }
+
+    /// Deserializes the file revision with provided Serde deserializer, leaving out the header.
+    pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let PrivateFileSerde {
+            version,
+            metadata,
+            content,
+            ..
+        } = PrivateFileSerde::deserialize(deserializer)?;
+
+        Ok(Self {
+            content: FileContent::from_serde(content, &version).map_err(DeError::custom)?,
+            version,
+            metadata,
+        })
+    }
+}
+
+impl FileContent {
+    /// Reads the content of a file with the given version. Files from before version 0.3.0 kept
+    /// their content as plain bytes.
+    fn from_serde(content: Ipld, version: &Version) -> Result<Self> {
+        if *version < PRIVATE_FILE_VERSION {
+            let data = ipld_serde::from_ipld::<Vec<u8>>(content)?;
+            return Ok(Self::Inline { data });
+        }
+
+        Ok(ipld_serde::from_ipld(content)?)
+    }
+
+    /// Streams the content one decrypted block at a time, starting at the given block index.
+    pub(crate) fn stream<'a, B: BlockStore>(
+        &'a self,
+        block_index: usize,
+        hamt: &'a PrivateForest,
+        store: &'a B,
+    ) -> impl Stream<Item = Result<Vec<u8>>> + 'a {
+        try_stream! {
+            match self {
+                Self::Inline { data } => {
+                    if block_index == 0 {
+                        yield data.clone();
+                    }
+                }
+                Self::External {
+                    key,
+                    block_count,
+                    base_name,
+                    cipher_suite,
+                    ..
+                } => {
+                    for index in block_index..*block_count {
+                        yield PrivateFile::decrypt_block(key, *cipher_suite, index, base_name, hamt, store).await?;
+                    }
+                }
+            }
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod private_file_tests {
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use test_log::test;
+
+    use super::*;
+    use crate::MemoryBlockStore;
+
+    #[test(async_std::test)]
+    async fn content_spanning_multiple_blocks_can_be_fetched() {
+        let store = &mut MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let hamt = Rc::new(PrivateForest::new());
+
+        let mut content = vec![0u8; MAX_BLOCK_CONTENT_SIZE * 2 + 100];
+        rng.fill_bytes(&mut content);
+
+        let (file, hamt) = PrivateFile::with_content(
+            Namefilter::default(),
+            Utc::now(),
+            content.clone(),
+            hamt,
+            store,
+            rng,
+        )
+        .await
+        .unwrap();
+
+        match &file.content {
+            FileContent::External { block_count, .. } => assert_eq!(*block_count, 3),
+            FileContent::Inline { .. } => panic!("Expected external content"),
+        }
+
+        let fetched = file.get_content(&hamt, store).await.unwrap();
+
+        assert_eq!(fetched, content);
+    }
+
+    #[test(async_std::test)]
+    async fn content_range_spanning_block_boundaries_can_be_fetched() {
+        let store = &mut MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let hamt = Rc::new(PrivateForest::new());
+
+        let mut content = vec![0u8; MAX_BLOCK_CONTENT_SIZE * 3];
+        rng.fill_bytes(&mut content);
+
+        let (file, hamt) = PrivateFile::with_content(
+            Namefilter::default(),
+            Utc::now(),
+            content.clone(),
+            hamt,
+            store,
+            rng,
+        )
+        .await
+        .unwrap();
+
+        let ranges = [
+            (0, 10),
+            (MAX_BLOCK_CONTENT_SIZE - 5, 10),
+            (MAX_BLOCK_CONTENT_SIZE - 5, MAX_BLOCK_CONTENT_SIZE + 10),
+            (MAX_BLOCK_CONTENT_SIZE, MAX_BLOCK_CONTENT_SIZE),
+            (MAX_BLOCK_CONTENT_SIZE * 3 - 5, 100),
+        ];
+
+        for (offset, len) in ranges {
+            let fetched = file
+                .get_content_range(offset, len, &hamt, store)
+                .await
+                .unwrap();
+
+            assert_eq!(fetched, utils::get_range(&content, offset, len));
+        }
+
+        let fetched = file
+            .get_content_range(MAX_BLOCK_CONTENT_SIZE * 3, 10, &hamt, store)
+            .await
+            .unwrap();
+
+        assert!(fetched.is_empty());
+    }
+
+    #[test(async_std::test)]
+    async fn content_blocks_fit_within_max_block_size() {
+        let store = &mut MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let hamt = Rc::new(PrivateForest::new());
+
+        let content = vec![7u8; MAX_BLOCK_CONTENT_SIZE];
+        let (file, hamt) =
+            PrivateFile::with_content(Namefilter::default(), Utc::now(), content, hamt, store, rng)
+                .await
+                .unwrap();
+
+        let (key, base_name) = match &file.content {
+            FileContent::External { key, base_name, .. } => (key, base_name),
+            FileContent::Inline { .. } => panic!("Expected external content"),
+        };
+
+        let label = PrivateFile::create_block_label(key, 0, base_name);
+        let cids = hamt
+            .get_encrypted(&Sha3_256::hash(&label.as_bytes()), store)
+            .await
+            .unwrap()
+            .unwrap();
+        let cid = cids.iter().next().unwrap();
+
+        assert_eq!(store.get_block(cid).await.unwrap().len(), MAX_BLOCK_SIZE);
+    }
+
+    #[test(async_std::test)]
+    async fn empty_content_can_be_fetched() {
+        let store = &mut MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let hamt = Rc::new(PrivateForest::new());
+
+        let (file, hamt) =
+            PrivateFile::with_content(Namefilter::default(), Utc::now(), vec![], hamt, store, rng)
+                .await
+                .unwrap();
+
+        assert!(file.get_content(&hamt, store).await.unwrap().is_empty());
+    }
+
+    #[test(async_std::test)]
+    async fn files_with_plain_byte_content_can_be_read() {
+        let store = &mut MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let file = PrivateFile::new(Namefilter::default(), Utc::now(), rng);
+        let key = file.header.get_private_ref().unwrap().ratchet_key;
+
+        // The form files were encoded in at version 0.2.0, with the content as plain bytes.
+        #[derive(Serialize)]
+        struct LegacyPrivateFileSerde {
+            r#type: NodeType,
+            version: Version,
+            header: Vec<u8>,
+            metadata: Metadata,
+            content: Vec<u8>,
+        }
+
+        let legacy = LegacyPrivateFileSerde {
+            r#type: NodeType::PrivateFile,
+            version: Version::new(0, 2, 0),
+            header: {
+                let cbor_bytes = dagcbor::encode(&file.header).unwrap();
+                CipherSuite::default()
+                    .encrypt(&key.0, &cbor_bytes, rng)
+                    .unwrap()
+            },
+            metadata: file.metadata.clone(),
+            content: b"hello world".to_vec(),
+        };
+        let ipld = ipld_serde::to_ipld(&legacy).unwrap();
+
+        let decoded = PrivateFile::deserialize(ipld.clone(), &key).unwrap();
+        let hamt = PrivateForest::new();
+
+        assert_eq!(decoded.version, PRIVATE_FILE_VERSION);
+        assert_eq!(decoded.header, file.header);
+        assert_eq!(
+            decoded.get_content(&hamt, store).await.unwrap(),
+            b"hello world"
+        );
+
+        let snapshot = PrivateFileSnapshot::deserialize(ipld).unwrap();
+
+        assert_eq!(snapshot.content, decoded.content);
+    }
+}
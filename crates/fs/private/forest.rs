@@ -1,13 +1,20 @@
-use std::rc::Rc;
+use std::{
+    collections::BTreeSet,
+    ops::{Deref, DerefMut},
+    rc::Rc,
+};
 
 use anyhow::Result;
-use libipld::Cid;
+use libipld::{Cid, Ipld};
 use log::debug;
 use rand_core::RngCore;
+use serde::{de::Error as DeError, Deserialize, Deserializer, Serialize};
 
 use crate::{BlockStore, HashOutput};
 
-use super::{hamt::Hamt, namefilter::Namefilter, Key, PrivateNode, PrivateRef};
+use super::{
+    hamt::Hamt, namefilter::Namefilter, PrivateNode, PrivateRef, PrivateSnapshot, SnapshotRef,
+};
 
 //--------------------------------------------------------------------------------------------------
 // Type Definitions
@@ -15,6 +22,9 @@ use super::{hamt::Hamt, namefilter::Namefilter, Key, PrivateNode, PrivateRef};
 
 /// PrivateForest is a HAMT that stores CIDs of encrypted private nodes keyed by saturated namefilters.
 ///
+/// Each key maps to a set of CIDs rather than a single one, so that concurrent writes to the same
+/// revision of a node are all kept when forests are merged instead of one overwriting the other.
+///
 /// On insert, nodes are serialized to DAG CBOR and encrypted with their private refs and then stored in
 /// an accompanying block store. And on lookup, the nodes are decrypted and deserialized with the same private
 /// refs.
@@ -30,20 +40,22 @@ use super::{hamt::Hamt, namefilter::Namefilter, Key, PrivateNode, PrivateRef};
 ///
 /// println!("{:?}", forest);
 /// ```
-// TODO(appcypher): Change Cid to PrivateLink<PrivateNode> to BTreeSet<PrivateLink<PrivateNode>>.
-pub type PrivateForest = Hamt<Namefilter, Cid>;
+pub type PrivateForest = Hamt<Namefilter, CidSet>;
+
+/// The CIDs stored at a key of the [`PrivateForest`].
+///
+/// Forests used to store a single CID per key. Such values are read as a set with one CID.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct CidSet(pub BTreeSet<Cid>);
 
 //--------------------------------------------------------------------------------------------------
 // Implementations
 //--------------------------------------------------------------------------------------------------
 
 impl PrivateForest {
-    /// Encrypts supplied bytes with a random nonce and AES key.
-    pub(crate) fn encrypt<R: RngCore>(key: &Key, data: &[u8], rng: &mut R) -> Result<Vec<u8>> {
-        key.encrypt(&Key::generate_nonce(rng), data)
-    }
-
-    /// Sets a new value at the given key.
+    /// Sets a new value at the given key. The value is encrypted with the cipher suite of the
+    /// private ref.
     ///
     /// # Examples
     ///
@@ -90,8 +102,11 @@ impl PrivateForest {
         // Serialize node to cbor.
         let cbor_bytes = value.serialize_to_cbor(rng)?;
 
-        // Encrypt bytes with content key.
-        let enc_bytes = Self::encrypt(&private_ref.content_key.0, &cbor_bytes, rng)?;
+        // Encrypt bytes with content key, using the cipher suite of the private ref.
+        let enc_bytes =
+            private_ref
+                .cipher_suite
+                .encrypt(&private_ref.content_key.0, &cbor_bytes, rng)?;
 
         // Store content section in blockstore and get Cid.
         let content_cid = store.put_block(enc_bytes, libipld::IpldCodec::Raw).await?;
@@ -102,6 +117,11 @@ impl PrivateForest {
 
     /// Gets the value at the given key.
     ///
+    /// If the node was written concurrently and the forests were merged afterwards, the key holds
+    /// several values. They are tried in Cid order and the first one that decrypts with the private
+    /// ref is returned, which is not necessarily the latest write. Use
+    /// [`get_multivalue`](Self::get_multivalue) to get all of them and resolve the conflict.
+    ///
     /// # Examples
     ///
     /// ```
@@ -141,26 +161,183 @@ impl PrivateForest {
     ) -> Result<Option<PrivateNode>> {
         debug!("Private Forest Get: PrivateRef: {:?}", private_ref);
 
-        // Fetch Cid from root node.
-        let cid = match self
+        // Fetch Cids from root node.
+        let cids = match self
+            .get_encrypted(&private_ref.saturated_name_hash, store)
+            .await?
+        {
+            Some(cids) => cids,
+            None => return Ok(None),
+        };
+
+        // Return the first value that decrypts, or the error of the last one if none does.
+        let mut error = None;
+        for cid in cids {
+            match Self::decrypt_node(cid, private_ref, store).await {
+                Ok(node) => return Ok(Some(node)),
+                Err(err) => error = Some(err),
+            }
+        }
+
+        error.map_or(Ok(None), Err)
+    }
+
+    /// Gets all the values at the given key. There is more than one value if the node was written
+    /// concurrently and the forests were merged afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{
+    ///     private::{PrivateForest, PrivateRef}, PrivateNode,
+    ///     BlockStore, MemoryBlockStore, Namefilter, PrivateDirectory, PrivateOpResult,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = Rc::new(PrivateForest::new());
+    ///     let dir = Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     let private_ref = &dir.header.get_private_ref().unwrap();
+    ///     let name = dir.header.get_saturated_name();
+    ///     let node = PrivateNode::Dir(dir);
+    ///
+    ///     let forest = forest.set(name, private_ref, &node, store, rng).await.unwrap();
+    ///     assert_eq!(forest.get_multivalue(private_ref, store).await.unwrap(), vec![node]);
+    /// }
+    /// ```
+    pub async fn get_multivalue<B: BlockStore>(
+        &self,
+        private_ref: &PrivateRef,
+        store: &B,
+    ) -> Result<Vec<PrivateNode>> {
+        debug!(
+            "Private Forest Get Multivalue: PrivateRef: {:?}",
+            private_ref
+        );
+
+        let cids = match self
             .get_encrypted(&private_ref.saturated_name_hash, store)
             .await?
         {
-            Some(value) => value,
+            Some(cids) => cids,
+            None => return Ok(vec![]),
+        };
+
+        let mut nodes = Vec::with_capacity(cids.len());
+        for cid in cids {
+            nodes.push(Self::decrypt_node(cid, private_ref, store).await?);
+        }
+
+        Ok(nodes)
+    }
+
+    /// Gets the single revision of a node that the snapshot ref points to. Only the content of the
+    /// node is decrypted, so no later revisions can be derived from it. If there were concurrent
+    /// writes, the first value that decrypts is returned, as with [`get`](Self::get).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{
+    ///     private::PrivateForest, MemoryBlockStore, Namefilter, PrivateDirectory, PrivateNode,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let forest = Rc::new(PrivateForest::new());
+    ///     let dir = Rc::new(PrivateDirectory::new(
+    ///         Namefilter::default(),
+    ///         Utc::now(),
+    ///         rng,
+    ///     ));
+    ///
+    ///     let private_ref = &dir.header.get_private_ref().unwrap();
+    ///     let name = dir.header.get_saturated_name();
+    ///     let node = PrivateNode::Dir(Rc::clone(&dir));
+    ///     let forest = forest.set(name, private_ref, &node, store, rng).await.unwrap();
+    ///
+    ///     let snapshot = forest
+    ///         .get_snapshot(&private_ref.as_snapshot_ref(), store)
+    ///         .await
+    ///         .unwrap()
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(snapshot.as_dir().unwrap().metadata, dir.metadata);
+    /// }
+    /// ```
+    pub async fn get_snapshot<B: BlockStore>(
+        &self,
+        snapshot_ref: &SnapshotRef,
+        store: &B,
+    ) -> Result<Option<PrivateSnapshot>> {
+        debug!(
+            "Private Forest Get Snapshot: SnapshotRef: {:?}",
+            snapshot_ref
+        );
+
+        let cids = match self
+            .get_encrypted(&snapshot_ref.saturated_name_hash, store)
+            .await?
+        {
+            Some(cids) => cids,
             None => return Ok(None),
         };
 
+        let mut error = None;
+        for cid in cids {
+            let snapshot = async {
+                let enc_bytes = store.get_block(cid).await?;
+                let cbor_bytes = snapshot_ref
+                    .cipher_suite
+                    .decrypt(&snapshot_ref.content_key.0, &enc_bytes)?;
+
+                PrivateSnapshot::deserialize_from_cbor(&cbor_bytes)
+            };
+
+            match snapshot.await {
+                Ok(snapshot) => return Ok(Some(snapshot)),
+                Err(err) => error = Some(err),
+            }
+        }
+
+        error.map_or(Ok(None), Err)
+    }
+
+    /// Fetches and decrypts the node stored at the given Cid.
+    async fn decrypt_node<B: BlockStore>(
+        cid: &Cid,
+        private_ref: &PrivateRef,
+        store: &B,
+    ) -> Result<PrivateNode> {
         // Fetch encrypted bytes from blockstore.
         let enc_bytes = store.get_block(cid).await?;
 
         // Decrypt bytes
-        let cbor_bytes = private_ref.content_key.0.decrypt(&enc_bytes)?;
+        let cbor_bytes = private_ref
+            .cipher_suite
+            .decrypt(&private_ref.content_key.0, &enc_bytes)?;
 
         // Deserialize bytes.
-        Ok(Some(PrivateNode::deserialize_from_cbor(
-            &cbor_bytes,
-            &private_ref.ratchet_key,
-        )?))
+        PrivateNode::deserialize_from_cbor(&cbor_bytes, &private_ref.ratchet_key)
     }
 
     /// Checks that a value with the given saturated name hash key exists.
@@ -213,38 +390,129 @@ impl PrivateForest {
             .is_some())
     }
 
-    /// Sets a new encrypted value at the given key.
+    /// Adds a new encrypted value at the given key. Values already at the key are kept.
     pub async fn set_encrypted<B: BlockStore>(
         self: Rc<Self>,
         name: Namefilter,
         value: Cid,
         store: &mut B,
     ) -> Result<Rc<Self>> {
+        let mut values = self
+            .root
+            .get(&name, store)
+            .await?
+            .cloned()
+            .unwrap_or_default();
+
+        values.insert(value);
+
         let mut cloned = (*self).clone();
-        cloned.root = self.root.set(name, value, store).await?;
+        cloned.root = self.root.set(name, values, store).await?;
         Ok(Rc::new(cloned))
     }
 
-    /// Gets the encrypted value at the given key.
+    /// Gets the encrypted values at the given key.
     #[inline]
     pub async fn get_encrypted<'b, B: BlockStore>(
         &'b self,
         name_hash: &HashOutput,
         store: &B,
-    ) -> Result<Option<&'b Cid>> {
-        self.root.get_by_hash(name_hash, store).await
+    ) -> Result<Option<&'b BTreeSet<Cid>>> {
+        Ok(self
+            .root
+            .get_by_hash(name_hash, store)
+            .await?
+            .map(|cids| &cids.0))
     }
 
-    /// Removes the encrypted value at the given key.
+    /// Removes the encrypted values at the given key.
     pub async fn remove_encrypted<B: BlockStore>(
         self: Rc<Self>,
         name_hash: &HashOutput,
         store: &mut B,
-    ) -> Result<(Rc<Self>, Option<Cid>)> {
+    ) -> Result<(Rc<Self>, Option<BTreeSet<Cid>>)> {
         let mut cloned = (*self).clone();
         let (root, pair) = cloned.root.remove_by_hash(name_hash, store).await?;
         cloned.root = root;
-        Ok((Rc::new(cloned), pair.map(|p| p.value)))
+        Ok((Rc::new(cloned), pair.map(|p| p.value.0)))
+    }
+
+    /// Merges two forests that diverged from the same base, for example because they were changed
+    /// on different devices.
+    ///
+    /// The merged forest has the entries of both forests. Keys present in both get the union of
+    /// their values, so concurrent writes to the same node are all kept. Nothing needs to be
+    /// decrypted and parts of the forests that are still the same are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{
+    ///     private::PrivateForest, PrivateNode, MemoryBlockStore, Namefilter, PrivateDirectory,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let base = Rc::new(PrivateForest::new());
+    ///
+    ///     let dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+    ///     let private_ref = &dir.header.get_private_ref().unwrap();
+    ///     let name = dir.header.get_saturated_name();
+    ///     let node = PrivateNode::Dir(dir);
+    ///
+    ///     let ours = Rc::clone(&base).set(name, private_ref, &node, store, rng).await.unwrap();
+    ///     let merged = ours.merge(&base, store).await.unwrap();
+    ///
+    ///     assert_eq!(merged.get(private_ref, store).await.unwrap(), Some(node));
+    /// }
+    /// ```
+    pub async fn merge<B: BlockStore>(&self, other: &Self, store: &mut B) -> Result<Rc<Self>> {
+        let root = Rc::clone(&self.root)
+            .merge(
+                &other.root,
+                |values, other_values| Ok(CidSet(values.union(other_values).cloned().collect())),
+                store,
+            )
+            .await?;
+
+        let mut merged = self.clone();
+        merged.root = root;
+        Ok(Rc::new(merged))
+    }
+}
+
+impl Deref for CidSet {
+    type Target = BTreeSet<Cid>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for CidSet {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for CidSet {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Ipld::deserialize(deserializer)? {
+            Ipld::Link(cid) => Ok(Self(BTreeSet::from([cid]))),
+            ipld => BTreeSet::deserialize(ipld)
+                .map(Self)
+                .map_err(DeError::custom),
+        }
     }
 }
 
@@ -261,7 +529,15 @@ mod hamt_store_tests {
     use chrono::Utc;
 
     use super::*;
-    use crate::{private::PrivateDirectory, MemoryBlockStore};
+    use crate::{private::PrivateDirectory, Hasher, MemoryBlockStore, PrivateOpResult};
+    use sha3::Sha3_256;
+
+    fn saturated_name(label: &str) -> Namefilter {
+        let mut name = Namefilter::default();
+        name.add(&label.as_bytes());
+        name.saturate();
+        name
+    }
 
     #[test(async_std::test)]
     async fn inserted_items_can_be_fetched() {
@@ -288,4 +564,222 @@ mod hamt_store_tests {
 
         assert_eq!(retrieved, private_node);
     }
+
+    #[test(async_std::test)]
+    async fn forests_with_single_cid_values_can_be_read() {
+        let store = &mut MemoryBlockStore::new();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let dir = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        let private_ref = dir.header.get_private_ref().unwrap();
+        let saturated_name = dir.header.get_saturated_name();
+        let private_node = PrivateNode::Dir(dir);
+
+        let content_cid = *Rc::new(PrivateForest::new())
+            .set(
+                saturated_name.clone(),
+                &private_ref,
+                &private_node,
+                store,
+                rng,
+            )
+            .await
+            .unwrap()
+            .get_encrypted(&private_ref.saturated_name_hash, store)
+            .await
+            .unwrap()
+            .unwrap()
+            .iter()
+            .next()
+            .unwrap();
+
+        // Forests used to map each name to a single Cid.
+        let mut old_forest = Hamt::<Namefilter, Cid>::new();
+        old_forest.root = old_forest
+            .root
+            .set(saturated_name, content_cid, store)
+            .await
+            .unwrap();
+        let old_forest_cid = store.put_async_serializable(&old_forest).await.unwrap();
+
+        let forest: PrivateForest = store.get_deserializable(&old_forest_cid).await.unwrap();
+
+        assert_eq!(
+            forest
+                .get_encrypted(&private_ref.saturated_name_hash, store)
+                .await
+                .unwrap(),
+            Some(&BTreeSet::from([content_cid]))
+        );
+        assert_eq!(
+            forest.get(&private_ref, store).await.unwrap(),
+            Some(private_node)
+        );
+    }
+
+    #[test(async_std::test)]
+    async fn values_that_do_not_decrypt_are_skipped() {
+        let store = &mut MemoryBlockStore::new();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let dir = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        let private_ref = dir.header.get_private_ref().unwrap();
+        let saturated_name = dir.header.get_saturated_name();
+        let private_node = PrivateNode::Dir(dir);
+
+        let forest = Rc::new(PrivateForest::new())
+            .set(
+                saturated_name.clone(),
+                &private_ref,
+                &private_node,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+        let content_cid = *forest
+            .get_encrypted(&private_ref.saturated_name_hash, store)
+            .await
+            .unwrap()
+            .unwrap()
+            .iter()
+            .next()
+            .unwrap();
+
+        // Add a value that comes before the node in Cid order but can't be decrypted.
+        let mut garbage = 0u64;
+        let garbage_cid = loop {
+            let cid = store
+                .put_block(garbage.to_le_bytes().to_vec(), libipld::IpldCodec::Raw)
+                .await
+                .unwrap();
+            if cid < content_cid {
+                break cid;
+            }
+
+            garbage += 1;
+        };
+        let forest = forest
+            .set_encrypted(saturated_name, garbage_cid, store)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            forest.get(&private_ref, store).await.unwrap(),
+            Some(private_node)
+        );
+        assert!(forest
+            .get_snapshot(&private_ref.as_snapshot_ref(), store)
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[test(async_std::test)]
+    async fn merge_unions_entries_and_keeps_concurrent_writes() {
+        let store = &mut MemoryBlockStore::new();
+        let base = Rc::new(PrivateForest::new())
+            .set_encrypted(saturated_name("shared"), Cid::default(), store)
+            .await
+            .unwrap();
+
+        let ours_cid = store.put_serializable(&"ours").await.unwrap();
+        let theirs_cid = store.put_serializable(&"theirs").await.unwrap();
+
+        let ours = Rc::clone(&base)
+            .set_encrypted(saturated_name("ours"), ours_cid, store)
+            .await
+            .unwrap()
+            .set_encrypted(saturated_name("conflict"), ours_cid, store)
+            .await
+            .unwrap();
+
+        let theirs = base
+            .set_encrypted(saturated_name("theirs"), theirs_cid, store)
+            .await
+            .unwrap()
+            .set_encrypted(saturated_name("conflict"), theirs_cid, store)
+            .await
+            .unwrap();
+
+        let merged = ours.merge(&theirs, store).await.unwrap();
+        let reverse_merged = theirs.merge(&ours, store).await.unwrap();
+
+        let get = |label: &str| Sha3_256::hash(&saturated_name(label).as_bytes());
+        let conflict = merged.get_encrypted(&get("conflict"), store).await.unwrap();
+
+        assert_eq!(conflict, Some(&BTreeSet::from([ours_cid, theirs_cid])));
+        assert!(merged.has(&get("shared"), store).await.unwrap());
+        assert!(merged.has(&get("ours"), store).await.unwrap());
+        assert!(merged.has(&get("theirs"), store).await.unwrap());
+        assert_eq!(
+            store.put_async_serializable(&merged).await.unwrap(),
+            store.put_async_serializable(&reverse_merged).await.unwrap()
+        );
+    }
+
+    #[test(async_std::test)]
+    async fn snapshots_give_access_to_a_single_revision_of_a_subtree() {
+        let store = &mut MemoryBlockStore::new();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let path = ["docs".to_string(), "notes.txt".to_string()];
+        let root_dir = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        let PrivateOpResult { root_dir, hamt, .. } = root_dir
+            .write(
+                &path,
+                true,
+                Utc::now(),
+                b"draft".to_vec(),
+                Rc::new(PrivateForest::new()),
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        let snapshot_ref = root_dir.header.get_private_ref().unwrap().as_snapshot_ref();
+
+        let PrivateOpResult { hamt, .. } = root_dir
+            .write(&path, true, Utc::now(), b"final".to_vec(), hamt, store, rng)
+            .await
+            .unwrap();
+
+        let root_snapshot = hamt
+            .get_snapshot(&snapshot_ref, store)
+            .await
+            .unwrap()
+            .unwrap();
+        let docs_snapshot = hamt
+            .get_snapshot(&root_snapshot.as_dir().unwrap().entries["docs"], store)
+            .await
+            .unwrap()
+            .unwrap();
+        let file_snapshot = hamt
+            .get_snapshot(&docs_snapshot.as_dir().unwrap().entries["notes.txt"], store)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let content = file_snapshot
+            .as_file()
+            .unwrap()
+            .get_content(&hamt, store)
+            .await
+            .unwrap();
+
+        assert_eq!(&content, b"draft");
+    }
 }
@@ -0,0 +1,266 @@
+use anyhow::Result;
+use async_recursion::async_recursion;
+use futures::TryStreamExt;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{BlockStore, ChangeType};
+
+use super::{Hasher, Node, Pair, Pointer, HAMT_BITMASK_BIT_SIZE};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A key that differs between two versions of a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyValueChange<K, V> {
+    pub r#type: ChangeType,
+    pub key: K,
+    /// The value in the main node, if it has the key.
+    pub main_value: Option<V>,
+    /// The value in the other node, if it has the key.
+    pub other_value: Option<V>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<K, V, H> Node<K, V, H>
+where
+    H: Hasher + Clone,
+{
+    /// Gets the keys that differ between this node, the main node, and another version of it.
+    ///
+    /// Keys only in the main node are reported as added and keys only in the other node as
+    /// removed, so the changes are the ones that turn the other node into the main node. Children
+    /// that have the same CID in both nodes are skipped without being loaded. The changes are
+    /// ordered by the hashes of their keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use wnfs::{private::Node, ChangeType, MemoryBlockStore};
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::new();
+    ///     let other = Rc::new(Node::<String, usize>::default());
+    ///     let other = other.set("removed".into(), 1, store).await.unwrap();
+    ///     let other = other.set("modified".into(), 2, store).await.unwrap();
+    ///
+    ///     let (main, _) = Rc::clone(&other).remove(&"removed".into(), store).await.unwrap();
+    ///     let main = main.set("modified".into(), 3, store).await.unwrap();
+    ///     let main = main.set("added".into(), 4, store).await.unwrap();
+    ///
+    ///     let mut changes = main.diff(&other, store).await.unwrap();
+    ///     changes.sort_by(|a, b| a.key.cmp(&b.key));
+    ///
+    ///     let changes = changes.iter().map(|c| (c.key.as_str(), c.r#type)).collect::<Vec<_>>();
+    ///     assert_eq!(
+    ///         changes,
+    ///         vec![
+    ///             ("added", ChangeType::Add),
+    ///             ("modified", ChangeType::Modify),
+    ///             ("removed", ChangeType::Remove),
+    ///         ]
+    ///     );
+    /// }
+    /// ```
+    pub async fn diff<B: BlockStore>(
+        &self,
+        other: &Self,
+        store: &mut B,
+    ) -> Result<Vec<KeyValueChange<K, V>>>
+    where
+        K: DeserializeOwned + Serialize + Clone + PartialEq,
+        V: DeserializeOwned + Serialize + Clone + PartialEq,
+    {
+        let mut changes = Vec::new();
+        self.diff_helper(other, &mut changes, store).await?;
+        Ok(changes)
+    }
+
+    #[async_recursion(?Send)]
+    async fn diff_helper<B>(
+        &self,
+        other: &Self,
+        changes: &mut Vec<KeyValueChange<K, V>>,
+        store: &mut B,
+    ) -> Result<()>
+    where
+        B: BlockStore,
+        K: DeserializeOwned + Serialize + Clone + PartialEq,
+        V: DeserializeOwned + Serialize + Clone + PartialEq,
+    {
+        let mut main_index = 0;
+        let mut other_index = 0;
+        for bit_index in 0..HAMT_BITMASK_BIT_SIZE {
+            let main_pointer = self.bitmask[bit_index].then(|| {
+                main_index += 1;
+                &self.pointers[main_index - 1]
+            });
+
+            let other_pointer = other.bitmask[bit_index].then(|| {
+                other_index += 1;
+                &other.pointers[other_index - 1]
+            });
+
+            if let (Some(Pointer::Link(main_link)), Some(Pointer::Link(other_link))) =
+                (main_pointer, other_pointer)
+            {
+                if !main_link.deep_eq(other_link, store).await? {
+                    let main_child = main_link.resolve_value(store).await?;
+                    let other_child = other_link.resolve_value(store).await?;
+                    main_child.diff_helper(other_child, changes, store).await?;
+                }
+
+                continue;
+            }
+
+            let main_pairs = collect_pointer_pairs(main_pointer, store).await?;
+            let other_pairs = collect_pointer_pairs(other_pointer, store).await?;
+            diff_pairs(main_pairs, other_pairs, changes);
+        }
+
+        Ok(())
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Collects all the pairs under an optional pointer.
+async fn collect_pointer_pairs<K, V, H, B>(
+    pointer: Option<&Pointer<K, V, H>>,
+    store: &B,
+) -> Result<Vec<Pair<K, V>>>
+where
+    K: DeserializeOwned + Clone,
+    V: DeserializeOwned + Clone,
+    H: Hasher + Clone,
+    B: BlockStore,
+{
+    Ok(match pointer {
+        Some(Pointer::Values(values)) => values.clone(),
+        Some(Pointer::Link(link)) => {
+            let child = link.resolve_value(store).await?;
+            child.stream(store).try_collect().await?
+        }
+        None => vec![],
+    })
+}
+
+/// Compares two lists of pairs by key.
+fn diff_pairs<K, V>(
+    main_pairs: Vec<Pair<K, V>>,
+    mut other_pairs: Vec<Pair<K, V>>,
+    changes: &mut Vec<KeyValueChange<K, V>>,
+) where
+    K: PartialEq,
+    V: PartialEq,
+{
+    for Pair { key, value } in main_pairs {
+        match other_pairs.iter().position(|pair| pair.key == key) {
+            Some(index) => {
+                let other_value = other_pairs.remove(index).value;
+                if value != other_value {
+                    changes.push(KeyValueChange {
+                        r#type: ChangeType::Modify,
+                        key,
+                        main_value: Some(value),
+                        other_value: Some(other_value),
+                    });
+                }
+            }
+            None => changes.push(KeyValueChange {
+                r#type: ChangeType::Add,
+                key,
+                main_value: Some(value),
+                other_value: None,
+            }),
+        }
+    }
+
+    changes.extend(
+        other_pairs
+            .into_iter()
+            .map(|Pair { key, value }| KeyValueChange {
+                r#type: ChangeType::Remove,
+                key,
+                main_value: None,
+                other_value: Some(value),
+            }),
+    );
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod hamt_diff_tests {
+    use std::{collections::BTreeMap, rc::Rc};
+
+    use super::*;
+    use crate::MemoryBlockStore;
+
+    #[async_std::test]
+    async fn diff_of_identical_nodes_is_empty() {
+        let store = &mut MemoryBlockStore::default();
+        let mut node = Rc::new(Node::<String, u64>::default());
+        for i in 0..200 {
+            node = node.set(format!("key {i}"), i, store).await.unwrap();
+        }
+
+        let changes = node.diff(&Rc::clone(&node), store).await.unwrap();
+
+        assert!(changes.is_empty());
+    }
+
+    #[async_std::test]
+    async fn diff_finds_changes_in_deeply_linked_nodes() {
+        let store = &mut MemoryBlockStore::default();
+        let mut other = Rc::new(Node::<String, u64>::default());
+        for i in 0..200 {
+            other = other.set(format!("key {i}"), i, store).await.unwrap();
+        }
+
+        let mut main = Rc::clone(&other);
+        (main, _) = main.remove(&"key 10".into(), store).await.unwrap();
+        main = main.set("key 20".into(), 1000, store).await.unwrap();
+        main = main.set("key 300".into(), 300, store).await.unwrap();
+
+        let changes = main
+            .diff(&other, store)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|change| (change.key.clone(), change))
+            .collect::<BTreeMap<_, _>>();
+
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes["key 10"].r#type, ChangeType::Remove);
+        assert_eq!(changes["key 10"].other_value, Some(10));
+        assert_eq!(changes["key 20"].r#type, ChangeType::Modify);
+        assert_eq!(changes["key 20"].main_value, Some(1000));
+        assert_eq!(changes["key 20"].other_value, Some(20));
+        assert_eq!(changes["key 300"].r#type, ChangeType::Add);
+    }
+
+    #[async_std::test]
+    async fn diff_against_empty_node_adds_every_key() {
+        let store = &mut MemoryBlockStore::default();
+        let mut main = Rc::new(Node::<String, u64>::default());
+        for i in 0..100 {
+            main = main.set(format!("key {i}"), i, store).await.unwrap();
+        }
+
+        let changes = main.diff(&Node::default(), store).await.unwrap();
+
+        assert_eq!(changes.len(), 100);
+        assert!(changes.iter().all(|c| c.r#type == ChangeType::Add));
+    }
+}
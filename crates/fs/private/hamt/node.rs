@@ -3,9 +3,10 @@ use std::{fmt::Debug, marker::PhantomData, rc::Rc};
 use crate::{private::HAMT_VALUES_BUCKET_SIZE, AsyncSerialize, BlockStore, HashOutput, Link};
 use anyhow::{bail, Result};
 use async_recursion::async_recursion;
+use async_stream::try_stream;
 use async_trait::async_trait;
 use bitvec::array::BitArray;
-
+use futures::Stream;
 use libipld::{serde as ipld_serde, Ipld};
 use log::debug;
 use serde::{
@@ -224,6 +225,158 @@ where
         self.remove_value(&mut HashNibbles::new(hash), store).await
     }
 
+    /// Merges the entries of another node into this node. When both nodes have a value at the same
+    /// key, the values are combined with `f`, which gets this node's value first.
+    ///
+    /// Only the keys reported by [`diff`](Self::diff) are looked at, so the cost depends on how much
+    /// the nodes differ rather than on their size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use wnfs::{private::Node, MemoryBlockStore};
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::new();
+    ///     let base = Rc::new(Node::<String, usize>::default());
+    ///     let base = base.set("shared".into(), 1, store).await.unwrap();
+    ///
+    ///     let main = base.set("main".into(), 2, store).await.unwrap();
+    ///     let main = main.set("shared".into(), 10, store).await.unwrap();
+    ///     let other = base.set("other".into(), 3, store).await.unwrap();
+    ///     let other = other.set("shared".into(), 20, store).await.unwrap();
+    ///
+    ///     let merged = main
+    ///         .merge(&other, |a, b| Ok(a + b), store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(merged.get(&String::from("main"), store).await.unwrap(), Some(&2));
+    ///     assert_eq!(merged.get(&String::from("other"), store).await.unwrap(), Some(&3));
+    ///     assert_eq!(merged.get(&String::from("shared"), store).await.unwrap(), Some(&30));
+    /// }
+    /// ```
+    pub async fn merge<B: BlockStore, F>(
+        self: Rc<Self>,
+        other: &Rc<Self>,
+        f: F,
+        store: &mut B,
+    ) -> Result<Rc<Self>>
+    where
+        K: DeserializeOwned + Serialize + Clone + PartialEq + AsRef<[u8]>,
+        V: DeserializeOwned + Serialize + Clone + PartialEq,
+        F: Fn(&V, &V) -> Result<V>,
+    {
+        let changes = self.diff(other, store).await?;
+
+        let mut merged = self;
+        for change in changes {
+            let value = match (change.main_value, change.other_value) {
+                (Some(value), Some(other_value)) => f(&value, &other_value)?,
+                (None, Some(other_value)) => other_value,
+                _ => continue,
+            };
+
+            merged = merged.set(change.key, value, store).await?;
+        }
+
+        Ok(merged)
+    }
+
+    /// Streams all the key-value pairs of the node, ordered by the hashes of their keys. Linked
+    /// children are only loaded from the store once the stream reaches them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use futures::TryStreamExt;
+    /// use wnfs::{private::Node, Pair, MemoryBlockStore};
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::new();
+    ///     let mut node = Rc::new(Node::<String, usize>::default());
+    ///     for i in 0..100 {
+    ///         node = node.set(format!("key {i}"), i, store).await.unwrap();
+    ///     }
+    ///
+    ///     let pairs = node.stream(store).try_collect::<Vec<_>>().await.unwrap();
+    ///
+    ///     assert_eq!(pairs.len(), 100);
+    ///     assert!(pairs.contains(&Pair::new("key 42".into(), 42)));
+    /// }
+    /// ```
+    pub fn stream<'a, B: BlockStore>(
+        self: &Rc<Self>,
+        store: &'a B,
+    ) -> impl Stream<Item = Result<Pair<K, V>>> + 'a
+    where
+        K: DeserializeOwned + Clone + 'a,
+        V: DeserializeOwned + Clone + 'a,
+        H: 'a,
+    {
+        let root = Rc::clone(self);
+        try_stream! {
+            // Each entry holds a node and the index of the next pointer to visit in it.
+            let mut stack = vec![(root, 0)];
+            while let Some((node, index)) = stack.pop() {
+                if let Some(pointer) = node.pointers.get(index) {
+                    stack.push((Rc::clone(&node), index + 1));
+                    match pointer {
+                        Pointer::Values(values) => {
+                            for pair in values {
+                                yield pair.clone();
+                            }
+                        }
+                        Pointer::Link(link) => {
+                            stack.push((Rc::clone(link.resolve_value(store).await?), 0));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Counts the key-value pairs of the node and all its children.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    /// use wnfs::{private::Node, MemoryBlockStore};
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::new();
+    ///     let mut node = Rc::new(Node::<String, usize>::default());
+    ///     for i in 0..100 {
+    ///         node = node.set(format!("key {i}"), i, store).await.unwrap();
+    ///     }
+    ///
+    ///     assert_eq!(node.len(store).await.unwrap(), 100);
+    /// }
+    /// ```
+    #[async_recursion(?Send)]
+    pub async fn len<B>(&self, store: &B) -> Result<usize>
+    where
+        B: BlockStore,
+        K: DeserializeOwned,
+        V: DeserializeOwned,
+    {
+        let mut len = 0;
+        for pointer in self.pointers.iter() {
+            len += match pointer {
+                Pointer::Values(values) => values.len(),
+                Pointer::Link(link) => link.resolve_value(store).await?.len(store).await?,
+            };
+        }
+
+        Ok(len)
+    }
+
     /// Checks if the node is empty.
     ///
     /// # Examples
@@ -451,7 +604,8 @@ where
 }
 
 impl<K, V, H: Hasher> Node<K, V, H> {
-    /// Returns the count of the values in all the values pointer of a node.
+    /// Returns the count of the values in all the values pointer of a node. Fails if the node has
+    /// linked children, use [`len`](Self::len) to count those as well.
     pub fn count_values(self: &Rc<Self>) -> Result<usize> {
         let mut len = 0;
         for i in self.pointers.iter() {
@@ -473,6 +627,19 @@ impl<K, V, H: Hasher> Node<K, V, H> {
         V: Serialize,
     {
         let bitmask_ipld = ipld_serde::to_ipld(self.bitmask.as_raw_slice())?;
+
+        // Store the children of all linked pointers in one batch, so the pointers below find
+        // their Cids cached.
+        let links = self
+            .pointers
+            .iter()
+            .filter_map(|pointer| match pointer {
+                Pointer::Link(link) => Some(link),
+                Pointer::Values(_) => None,
+            })
+            .collect::<Vec<_>>();
+        Link::resolve_cids(&links, store).await?;
+
         let pointers_ipld = {
             let mut tmp = Vec::with_capacity(self.pointers.len());
             for pointer in self.pointers.iter() {
@@ -552,6 +719,7 @@ where
 mod hamt_node_unit_tests {
     use super::*;
     use crate::{HashOutput, MemoryBlockStore};
+    use futures::TryStreamExt;
     use lazy_static::lazy_static;
     use test_log::test;
 
@@ -749,6 +917,29 @@ mod hamt_node_unit_tests {
         assert_eq!(value, &(10, 0.315));
     }
 
+    #[test(async_std::test)]
+    async fn stream_and_len_include_values_in_linked_nodes() {
+        let store = &mut MemoryBlockStore::default();
+        let mut node = Rc::new(Node::<String, u64>::default());
+        for i in 0..500 {
+            node = node.set(format!("key {i}"), i, store).await.unwrap();
+        }
+
+        (node, _) = node.remove(&"key 250".into(), store).await.unwrap();
+
+        let pairs = node.stream(store).try_collect::<Vec<_>>().await.unwrap();
+        let hashes = pairs
+            .iter()
+            .map(|pair| Sha3_256::hash(&pair.key))
+            .collect::<Vec<_>>();
+
+        assert!(node.count_values().is_err());
+        assert_eq!(node.len(store).await.unwrap(), 499);
+        assert_eq!(pairs.len(), 499);
+        assert!(hashes.windows(2).all(|w| w[0] < w[1]));
+        assert!(!pairs.iter().any(|pair| pair.key == "key 250"));
+    }
+
     #[test(async_std::test)]
     async fn node_is_same_with_irrelevant_remove() {
         // These two keys' hashes have the same first nibble (7)
@@ -1053,6 +1244,52 @@ mod hamt_node_prop_tests {
         })
     }
 
+    #[proptest(cases = 100)]
+    fn node_merge_matches_hash_map_union(
+        #[strategy(operations(small_key(), 0u64..1000, 0..100))] operations1: Operations<
+            String,
+            u64,
+        >,
+        #[strategy(operations(small_key(), 0u64..1000, 0..100))] operations2: Operations<
+            String,
+            u64,
+        >,
+    ) {
+        async_std::task::block_on(async move {
+            let store = &mut MemoryBlockStore::default();
+            let node1 = node_from_operations(operations1.clone(), store)
+                .await
+                .unwrap();
+            let node2 = node_from_operations(operations2.clone(), store)
+                .await
+                .unwrap();
+
+            let mut map = hash_map_from_operations(operations1);
+            for (key, value) in hash_map_from_operations(operations2) {
+                let entry = map.entry(key).or_insert(value);
+                *entry = value.max(*entry);
+            }
+
+            let inserts = map
+                .into_iter()
+                .map(|(key, value)| Operation::Insert(key, value))
+                .collect();
+            let expected = node_from_operations(Operations(inserts), store)
+                .await
+                .unwrap();
+
+            let merged = node1
+                .merge(&node2, |a, b| Ok(*a.max(b)), store)
+                .await
+                .unwrap();
+
+            let cid1 = store.put_async_serializable(&merged).await.unwrap();
+            let cid2 = store.put_async_serializable(&expected).await.unwrap();
+
+            assert_eq!(cid1, cid2);
+        })
+    }
+
     // This is sort of a "control group" for making sure that operations_and_shuffled is correct.
     #[proptest(cases = 200, max_shrink_iters = 10_000)]
     fn hash_map_is_history_independent(
@@ -0,0 +1,213 @@
+//! Version history of private nodes.
+
+use anyhow::Result;
+use async_stream::try_stream;
+use futures::Stream;
+use skip_ratchet::Ratchet;
+
+use crate::{BlockStore, FsError};
+
+use super::{PrivateForest, PrivateNode};
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl PrivateNode {
+    /// Streams the earlier revisions of this node, newest first, by rewinding its ratchet back to
+    /// a known past ratchet. The revision at the past ratchet is the last one yielded.
+    ///
+    /// Ratchets are one-way, so the past ratchet has to be known up front, e.g. from an older
+    /// private ref to the node. `discrepancy_budget` bounds how many revisions the ratchet may be
+    /// rewound by. Revisions missing from the forest are skipped, and all the values of revisions
+    /// that were written concurrently are yielded. Revisions stored under another name, like ones
+    /// from before the node was moved, are not found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use futures::TryStreamExt;
+    /// use rand::thread_rng;
+    ///
+    /// use wnfs::{private::PrivateForest, MemoryBlockStore, Namefilter, PrivateDirectory};
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let path = ["notes.txt".to_string()];
+    ///     let root_dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+    ///     let hamt = Rc::new(PrivateForest::new());
+    ///
+    ///     let result = root_dir
+    ///         .write(&path, true, Utc::now(), b"draft".to_vec(), hamt, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///     let result = result
+    ///         .root_dir
+    ///         .get_node(&path, true, result.hamt, store)
+    ///         .await
+    ///         .unwrap();
+    ///     let past_ratchet = result.result.unwrap().get_header().get_ratchet().clone();
+    ///
+    ///     let result = result
+    ///         .root_dir
+    ///         .write(&path, true, Utc::now(), b"final".to_vec(), result.hamt, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///     let result = result
+    ///         .root_dir
+    ///         .get_node(&path, true, result.hamt, store)
+    ///         .await
+    ///         .unwrap();
+    ///     let file = result.result.unwrap();
+    ///
+    ///     let revisions = file
+    ///         .history(&past_ratchet, 1_000, &result.hamt, store)
+    ///         .try_collect::<Vec<_>>()
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(revisions.len(), 1);
+    ///
+    ///     let content = revisions[0]
+    ///         .as_file()
+    ///         .unwrap()
+    ///         .get_content(&result.hamt, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(&content, b"draft");
+    /// }
+    /// ```
+    pub fn history<'a, B: BlockStore>(
+        &self,
+        past_ratchet: &Ratchet,
+        discrepancy_budget: usize,
+        forest: &'a PrivateForest,
+        store: &'a B,
+    ) -> impl Stream<Item = Result<PrivateNode>> + 'a {
+        let mut header = self.get_header().clone();
+        let past_ratchet = past_ratchet.clone();
+        try_stream! {
+            if header.ratchet != past_ratchet {
+                let ratchets = header
+                    .ratchet
+                    .previous(&past_ratchet, discrepancy_budget)
+                    .map_err(|err| FsError::UnableToRewindRatchet(err.to_string()))?;
+
+                for ratchet in ratchets {
+                    header.ratchet = ratchet;
+                    let private_ref = header.get_private_ref()?;
+                    for node in forest.get_multivalue(&private_ref, store).await? {
+                        yield node;
+                    }
+                }
+            }
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod private_history_tests {
+    use std::rc::Rc;
+
+    use chrono::Utc;
+    use futures::TryStreamExt;
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use test_log::test;
+
+    use super::*;
+    use crate::{MemoryBlockStore, Namefilter, PrivateDirectory, PrivateOpResult};
+
+    #[test(async_std::test)]
+    async fn history_yields_earlier_revisions_newest_first() {
+        let store = &mut MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let path = ["notes.txt".to_string()];
+        let mut root_dir = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+        let mut hamt = Rc::new(PrivateForest::new());
+        let mut ratchets = vec![];
+
+        for content in ["one", "two", "three", "four"] {
+            let result = root_dir
+                .write(&path, true, Utc::now(), content.into(), hamt, store, rng)
+                .await
+                .unwrap();
+
+            let PrivateOpResult {
+                root_dir: new_root_dir,
+                hamt: new_hamt,
+                result,
+            } = result
+                .root_dir
+                .get_node(&path, true, result.hamt, store)
+                .await
+                .unwrap();
+
+            ratchets.push(result.unwrap().get_header().get_ratchet().clone());
+            root_dir = new_root_dir;
+            hamt = new_hamt;
+        }
+
+        let file = root_dir
+            .get_node(&path, true, Rc::clone(&hamt), store)
+            .await
+            .unwrap()
+            .result
+            .unwrap();
+
+        let mut contents = vec![];
+        let mut revisions = Box::pin(file.history(&ratchets[1], 1_000, &hamt, store));
+        while let Some(revision) = revisions.try_next().await.unwrap() {
+            let content = revision
+                .as_file()
+                .unwrap()
+                .get_content(&hamt, store)
+                .await
+                .unwrap();
+
+            contents.push(String::from_utf8(content).unwrap());
+        }
+
+        assert_eq!(contents, vec!["three", "two"]);
+
+        let revisions = file
+            .history(&ratchets[3], 1_000, &hamt, store)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        assert!(revisions.is_empty());
+    }
+
+    #[test(async_std::test)]
+    async fn history_fails_when_budget_is_exceeded() {
+        let store = &mut MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let hamt = PrivateForest::new();
+        let mut dir = PrivateDirectory::new(Namefilter::default(), Utc::now(), rng);
+        let past_ratchet = dir.header.get_ratchet().clone();
+
+        dir.header.ratchet.inc_by(100_000);
+        let node = PrivateNode::from(dir);
+
+        let result = node
+            .history(&past_ratchet, 10, &hamt, store)
+            .try_collect::<Vec<_>>()
+            .await;
+
+        assert!(result.is_err());
+    }
+}
@@ -4,9 +4,12 @@ use aes_gcm::{
     aead::{Aead, NewAead},
     Aes256Gcm, Key as AesKey, Nonce,
 };
-use anyhow::Result;
+use anyhow::{bail, Result};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
 use rand_core::RngCore;
 use serde::{Deserialize, Serialize};
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 use crate::{utils, FsError};
 
@@ -15,6 +18,8 @@ use crate::{utils, FsError};
 //--------------------------------------------------------------------------------------------------
 
 pub(crate) const NONCE_SIZE: usize = 12;
+pub(crate) const XCHACHA_NONCE_SIZE: usize = 24;
+pub(crate) const AUTHENTICATION_TAG_SIZE: usize = 16;
 
 //--------------------------------------------------------------------------------------------------
 // Type Definitions
@@ -22,6 +27,9 @@ pub(crate) const NONCE_SIZE: usize = 12;
 
 /// A key used for encryption and decryption. This is a wrapper around a 32 byte AES key.
 ///
+/// The key bytes are zeroed when the key is dropped, keys are compared in constant time and
+/// the `Debug` output does not show the bytes.
+///
 /// # Examples
 ///
 /// ```
@@ -33,9 +41,39 @@ pub(crate) const NONCE_SIZE: usize = 12;
 ///
 /// println!("Key: {:?}", key);
 /// ```
-#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
 pub struct Key(pub(super) [u8; 32]);
 
+/// The authenticated cipher private nodes and their content are encrypted with.
+///
+/// The cipher suite is recorded in private refs and nodes, so data encrypted with different cipher
+/// suites can live in the same private forest. AES-256-GCM is the default, as it is what all data
+/// written before cipher suites were introduced is encrypted with.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs::{private::{CipherSuite, Key}, utils};
+/// use rand::thread_rng;
+///
+/// let rng = &mut thread_rng();
+/// let key = Key::new(utils::get_random_bytes(rng));
+/// let suite = CipherSuite::XChaCha20Poly1305;
+///
+/// let ciphertext = suite.encrypt(&key, b"Hello World!", rng).unwrap();
+///
+/// assert_eq!(suite.decrypt(&key, &ciphertext).unwrap(), b"Hello World!");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CipherSuite {
+    /// AES-256-GCM with 12-byte random nonces.
+    #[default]
+    Aes256Gcm,
+    /// XChaCha20-Poly1305 with 24-byte random nonces. Random nonces this long are safe to use for
+    /// practically any number of messages, and the cipher is fast without hardware AES support.
+    XChaCha20Poly1305,
+}
+
 //--------------------------------------------------------------------------------------------------
 // Implementations
 //--------------------------------------------------------------------------------------------------
@@ -133,30 +171,94 @@ impl Key {
         utils::get_random_bytes::<NONCE_SIZE>(rng)
     }
 
-    /// Grabs the bytes of the key.
-    pub fn bytes(self) -> [u8; 32] {
-        self.0
-    }
-
     /// Gets the bytes of the key.
     pub fn as_bytes(&self) -> &[u8] {
         &self.0
     }
 }
 
-impl Debug for Key {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "0x")?;
-        for (i, byte) in self.0.iter().enumerate() {
-            if i > 6 {
-                write!(f, "..")?;
-                break;
-            } else {
-                write!(f, "{:02X}", byte)?;
+impl CipherSuite {
+    /// Encrypts the given plaintext with a random nonce. The nonce is prepended to the ciphertext.
+    pub fn encrypt<R: RngCore>(&self, key: &Key, data: &[u8], rng: &mut R) -> Result<Vec<u8>> {
+        match self {
+            Self::Aes256Gcm => key.encrypt(&Key::generate_nonce(rng), data),
+            Self::XChaCha20Poly1305 => {
+                let nonce_bytes = utils::get_random_bytes::<XCHACHA_NONCE_SIZE>(rng);
+                let cipher_text = XChaCha20Poly1305::new(&key.0.into())
+                    .encrypt(XNonce::from_slice(&nonce_bytes), data)
+                    .map_err(|e| FsError::UnableToEncrypt(format!("{}", e)))?;
+
+                Ok([nonce_bytes.to_vec(), cipher_text].concat())
             }
         }
+    }
+
+    /// Decrypts the given ciphertext, which starts with the nonce it was encrypted with.
+    pub fn decrypt(&self, key: &Key, cipher_text: &[u8]) -> Result<Vec<u8>> {
+        if cipher_text.len() < self.nonce_size() {
+            bail!(FsError::UnableToDecrypt("Ciphertext is too short".into()));
+        }
+
+        match self {
+            Self::Aes256Gcm => key.decrypt(cipher_text),
+            Self::XChaCha20Poly1305 => {
+                let (nonce_bytes, data) = cipher_text.split_at(XCHACHA_NONCE_SIZE);
+
+                Ok(XChaCha20Poly1305::new(&key.0.into())
+                    .decrypt(XNonce::from_slice(nonce_bytes), data)
+                    .map_err(|e| FsError::UnableToDecrypt(format!("{}", e)))?)
+            }
+        }
+    }
+
+    /// Gets the number of bytes in the nonces of the cipher suite.
+    pub fn nonce_size(&self) -> usize {
+        match self {
+            Self::Aes256Gcm => NONCE_SIZE,
+            Self::XChaCha20Poly1305 => XCHACHA_NONCE_SIZE,
+        }
+    }
+
+    /// Gets the byte the cipher suite is identified by in binary encodings.
+    pub(crate) fn to_byte(self) -> u8 {
+        match self {
+            Self::Aes256Gcm => 0,
+            Self::XChaCha20Poly1305 => 1,
+        }
+    }
+
+    /// Gets the cipher suite identified by a byte from [`CipherSuite::to_byte`].
+    pub(crate) fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Aes256Gcm),
+            1 => Some(Self::XChaCha20Poly1305),
+            _ => None,
+        }
+    }
 
-        Ok(())
+    /// Checks if this is the default cipher suite, which is left out when serializing.
+    pub(crate) fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl ConstantTimeEq for Key {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.0.ct_eq(&other.0)
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Eq for Key {}
+
+impl Debug for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Key").field(&"<hidden>").finish()
     }
 }
 
@@ -164,6 +266,28 @@ impl Debug for Key {
 // Tests
 //--------------------------------------------------------------------------------------------------
 
+#[cfg(test)]
+mod key_tests {
+    use super::*;
+
+    #[test]
+    fn key_debug_output_hides_bytes() {
+        let key = Key::new([0xAB; 32]);
+        let output = format!("{key:?}");
+
+        assert_eq!(output, r#"Key("<hidden>")"#);
+    }
+
+    #[test]
+    fn key_is_zeroed_by_zeroize() {
+        let mut key = Key::new([0xAB; 32]);
+        key.zeroize();
+
+        assert_eq!(key.as_bytes(), &[0; 32]);
+        assert_ne!(key, Key::new([0xAB; 32]));
+    }
+}
+
 #[cfg(test)]
 mod key_prop_tests {
     use super::*;
@@ -187,4 +311,35 @@ mod key_prop_tests {
 
         assert_eq!(decrypted, data);
     }
+
+    #[proptest(cases = 100)]
+    fn cipher_suites_can_encrypt_and_decrypt_data(
+        #[strategy(any::<Vec<u8>>())] data: Vec<u8>,
+        #[strategy(any::<[u8; 32]>())] rng_seed: [u8; 32],
+        key_bytes: [u8; 32],
+    ) {
+        let key = Key::new(key_bytes);
+        let rng = &mut TestRng::from_seed(RngAlgorithm::ChaCha, &rng_seed);
+
+        for suite in [CipherSuite::Aes256Gcm, CipherSuite::XChaCha20Poly1305] {
+            let encrypted = suite.encrypt(&key, &data, rng).unwrap();
+
+            assert_eq!(
+                encrypted.len(),
+                suite.nonce_size() + data.len() + AUTHENTICATION_TAG_SIZE
+            );
+            assert_eq!(suite.decrypt(&key, &encrypted).unwrap(), data);
+        }
+
+        // AES-256-GCM ciphertexts are the ones keys have always produced.
+        let encrypted = key.encrypt(&Key::generate_nonce(rng), &data).unwrap();
+
+        assert_eq!(
+            CipherSuite::Aes256Gcm.decrypt(&key, &encrypted).unwrap(),
+            data
+        );
+        assert!(CipherSuite::XChaCha20Poly1305
+            .decrypt(&key, &encrypted)
+            .is_err());
+    }
 }
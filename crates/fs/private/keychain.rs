@@ -0,0 +1,241 @@
+//! Persistent, encrypted storage of private refs.
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use anyhow::{bail, Result};
+use libipld::{serde as ipld_serde, Cid, Ipld};
+use rand_core::RngCore;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::{BlockStore, FsError};
+
+use super::{Key, PrivateRef};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The version of the keychain format written by [`Keychain::store`].
+pub const KEYCHAIN_VERSION: Version = Version::new(0, 1, 0);
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A collection of named private refs, like the root of a private filesystem, folders shared with
+/// the user or folders of apps.
+///
+/// A keychain is stored in a block store as a single block encrypted with a master key, so a user
+/// only has to keep the master key and the CID of the keychain to get back to their private refs.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::Utc;
+/// use rand::thread_rng;
+/// use wnfs::{
+///     private::{Key, Keychain},
+///     MemoryBlockStore, Namefilter, PrivateDirectory,
+/// };
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &mut MemoryBlockStore::default();
+///     let rng = &mut thread_rng();
+///     let master_key = Key::new(rand::random());
+///     let dir = PrivateDirectory::new(Namefilter::default(), Utc::now(), rng);
+///
+///     let mut keychain = Keychain::new();
+///     keychain.insert("root", dir.header.get_private_ref().unwrap());
+///
+///     let cid = keychain.store(&master_key, store, rng).await.unwrap();
+///     let loaded = Keychain::load(&cid, &master_key, store).await.unwrap();
+///
+///     assert_eq!(loaded, keychain);
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Keychain {
+    entries: BTreeMap<String, PrivateRef>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeychainSerializable {
+    version: Version,
+    entries: BTreeMap<String, PrivateRef>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl Keychain {
+    /// Creates an empty keychain.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a private ref under the given name, returning the private ref it replaces.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{private::Keychain, Namefilter, PrivateDirectory};
+    ///
+    /// let rng = &mut thread_rng();
+    /// let dir = PrivateDirectory::new(Namefilter::default(), Utc::now(), rng);
+    /// let private_ref = dir.header.get_private_ref().unwrap();
+    ///
+    /// let mut keychain = Keychain::new();
+    /// keychain.insert("root", private_ref.clone());
+    ///
+    /// assert_eq!(keychain.get("root"), Some(&private_ref));
+    /// ```
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        private_ref: PrivateRef,
+    ) -> Option<PrivateRef> {
+        self.entries.insert(name.into(), private_ref)
+    }
+
+    /// Gets the private ref stored under the given name.
+    pub fn get(&self, name: &str) -> Option<&PrivateRef> {
+        self.entries.get(name)
+    }
+
+    /// Removes the private ref stored under the given name and returns it.
+    pub fn remove(&mut self, name: &str) -> Option<PrivateRef> {
+        self.entries.remove(name)
+    }
+
+    /// Iterates over the names and private refs in the keychain, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &PrivateRef)> {
+        self.entries.iter()
+    }
+
+    /// Encrypts the keychain with the master key and stores it in the block store.
+    pub async fn store<B, R>(&self, master_key: &Key, store: &mut B, rng: &mut R) -> Result<Cid>
+    where
+        B: BlockStore,
+        R: RngCore,
+    {
+        let serializable = KeychainSerializable {
+            version: KEYCHAIN_VERSION,
+            entries: self.entries.clone(),
+        };
+
+        store
+            .put_private_serializable(&serializable, master_key, rng)
+            .await
+    }
+
+    /// Loads and decrypts a keychain stored with [`Keychain::store`].
+    ///
+    /// Keychains written in an older format are migrated to the current one. Keychains written by
+    /// a newer, incompatible version are rejected.
+    pub async fn load<B: BlockStore>(cid: &Cid, master_key: &Key, store: &B) -> Result<Self> {
+        let ipld = store
+            .get_private_deserializable::<Ipld>(cid, master_key)
+            .await?;
+
+        let KeychainSerializable { entries, .. } = Self::migrate(ipld)?;
+
+        Ok(Self { entries })
+    }
+
+    /// Upgrades a serialized keychain to the current format.
+    ///
+    /// When the format changes, add an arm here that rewrites the older IPLD into the next
+    /// version, so keychains persisted by earlier releases keep loading.
+    fn migrate(ipld: Ipld) -> Result<KeychainSerializable> {
+        let version = match &ipld {
+            Ipld::Map(map) => match map.get("version") {
+                Some(Ipld::String(version)) => Version::from_str(version)?,
+                _ => bail!(FsError::InvalidDeserialization(
+                    "Missing keychain version".into()
+                )),
+            },
+            other => bail!(FsError::InvalidDeserialization(format!(
+                "Expected `Ipld::Map`, got {other:?}"
+            ))),
+        };
+
+        match version {
+            Version { major, minor, .. }
+                if major == KEYCHAIN_VERSION.major && minor == KEYCHAIN_VERSION.minor =>
+            {
+                Ok(ipld_serde::from_ipld(ipld)?)
+            }
+            _ => bail!(FsError::UnsupportedKeychainVersion(version)),
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod keychain_tests {
+    use chrono::Utc;
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use test_log::test;
+
+    use super::*;
+    use crate::{utils, MemoryBlockStore, Namefilter, PrivateDirectory};
+
+    fn generate_private_ref(rng: &mut TestRng) -> PrivateRef {
+        PrivateDirectory::new(Namefilter::default(), Utc::now(), rng)
+            .header
+            .get_private_ref()
+            .unwrap()
+    }
+
+    #[test(async_std::test)]
+    async fn keychain_can_be_stored_and_loaded() {
+        let store = &mut MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let master_key = Key::new(utils::get_random_bytes(rng));
+
+        let mut keychain = Keychain::new();
+        keychain.insert("root", generate_private_ref(rng));
+        keychain.insert("shared-with-me", generate_private_ref(rng));
+        keychain.insert("apps/notes", generate_private_ref(rng));
+
+        let cid = keychain.store(&master_key, store, rng).await.unwrap();
+        let loaded = Keychain::load(&cid, &master_key, store).await.unwrap();
+
+        assert_eq!(loaded, keychain);
+
+        let wrong_key = Key::new(utils::get_random_bytes(rng));
+
+        assert!(Keychain::load(&cid, &wrong_key, store).await.is_err());
+    }
+
+    #[test(async_std::test)]
+    async fn keychains_with_unknown_versions_are_rejected() {
+        let store = &mut MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let master_key = Key::new(utils::get_random_bytes(rng));
+
+        let serializable = KeychainSerializable {
+            version: Version::new(1, 0, 0),
+            entries: BTreeMap::new(),
+        };
+        let cid = store
+            .put_private_serializable(&serializable, &master_key, rng)
+            .await
+            .unwrap();
+
+        let error = Keychain::load(&cid, &master_key, store).await.unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<FsError>(),
+            Some(FsError::UnsupportedKeychainVersion(_))
+        ));
+    }
+}
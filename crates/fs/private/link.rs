@@ -0,0 +1,314 @@
+//! Portable encoding of private refs.
+
+use std::{fmt, io::Cursor, str::FromStr};
+
+use anyhow::{bail, ensure, Result};
+use libipld::{
+    multibase::{self, Base},
+    Cid,
+};
+
+use crate::{FsError, HASH_BYTE_SIZE};
+
+use super::{CipherSuite, Key, PrivateRef};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The first byte of an encoded private link, which marks the bytes as one.
+pub const PRIVATE_LINK_TYPE: u8 = 0x70;
+
+/// The version of the private link encoding.
+pub const PRIVATE_LINK_VERSION: u8 = 2;
+
+/// The number of bytes before the forest CID in an encoded private link.
+const PRIVATE_LINK_HEADER_SIZE: usize = 3 + 3 * HASH_BYTE_SIZE;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A private ref along with the CID of the private forest it can be opened in. It can be encoded
+/// as a string to be copied between devices or apps.
+///
+/// The binary encoding is a type byte, a version byte, a cipher suite byte, the saturated name
+/// hash, the content key, the ratchet key and the forest CID, in that order. The string encoding
+/// is the binary encoding in multibase. Links of version 1, which have no cipher suite byte and
+/// always use AES-256-GCM, can still be decoded.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::Utc;
+/// use libipld::Cid;
+/// use rand::thread_rng;
+/// use wnfs::{private::PrivateLink, Namefilter, PrivateDirectory};
+///
+/// let rng = &mut thread_rng();
+/// let dir = PrivateDirectory::new(Namefilter::default(), Utc::now(), rng);
+/// let link = PrivateLink::new(dir.header.get_private_ref().unwrap(), Cid::default());
+///
+/// let parsed = link.to_string().parse::<PrivateLink>().unwrap();
+///
+/// assert_eq!(parsed, link);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrivateLink {
+    pub private_ref: PrivateRef,
+    /// The CID of the root of the private forest.
+    pub forest_cid: Cid,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl PrivateLink {
+    /// Creates a private link.
+    pub fn new(private_ref: PrivateRef, forest_cid: Cid) -> Self {
+        Self {
+            private_ref,
+            forest_cid,
+        }
+    }
+
+    /// Encodes the private link to bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::Utc;
+    /// use libipld::Cid;
+    /// use rand::thread_rng;
+    /// use wnfs::{private::PrivateLink, Namefilter, PrivateDirectory};
+    ///
+    /// let rng = &mut thread_rng();
+    /// let dir = PrivateDirectory::new(Namefilter::default(), Utc::now(), rng);
+    /// let link = PrivateLink::new(dir.header.get_private_ref().unwrap(), Cid::default());
+    ///
+    /// let bytes = link.to_bytes();
+    ///
+    /// assert_eq!(PrivateLink::from_bytes(&bytes).unwrap(), link);
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let PrivateRef {
+            saturated_name_hash,
+            content_key,
+            ratchet_key,
+            cipher_suite,
+        } = &self.private_ref;
+
+        let cid_bytes = self.forest_cid.to_bytes();
+        let mut bytes = Vec::with_capacity(PRIVATE_LINK_HEADER_SIZE + cid_bytes.len());
+        bytes.push(PRIVATE_LINK_TYPE);
+        bytes.push(PRIVATE_LINK_VERSION);
+        bytes.push(cipher_suite.to_byte());
+        bytes.extend_from_slice(saturated_name_hash);
+        bytes.extend_from_slice(content_key.0.as_bytes());
+        bytes.extend_from_slice(ratchet_key.0.as_bytes());
+        bytes.extend(cid_bytes);
+        bytes
+    }
+
+    /// Decodes a private link from bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        ensure!(
+            bytes.len() > 2,
+            FsError::InvalidPrivateLink(format!("Expected more than 2 bytes, got {}", bytes.len()))
+        );
+
+        if bytes[0] != PRIVATE_LINK_TYPE {
+            bail!(FsError::InvalidPrivateLink(format!(
+                "Unknown type {:#x}",
+                bytes[0]
+            )));
+        }
+
+        // Version 1 links have no cipher suite byte.
+        let (cipher_suite, rest) = match bytes[1] {
+            1 => (CipherSuite::Aes256Gcm, &bytes[2..]),
+            PRIVATE_LINK_VERSION => {
+                let cipher_suite = CipherSuite::from_byte(bytes[2]).ok_or_else(|| {
+                    FsError::InvalidPrivateLink(format!("Unknown cipher suite {:#x}", bytes[2]))
+                })?;
+
+                (cipher_suite, &bytes[3..])
+            }
+            version => bail!(FsError::UnsupportedPrivateLinkVersion(version)),
+        };
+
+        ensure!(
+            rest.len() > 3 * HASH_BYTE_SIZE,
+            FsError::InvalidPrivateLink(format!(
+                "Expected more than {} bytes after the header, got {}",
+                3 * HASH_BYTE_SIZE,
+                rest.len()
+            ))
+        );
+
+        let (hashes, cid_bytes) = rest.split_at(3 * HASH_BYTE_SIZE);
+        let mut chunks = hashes
+            .chunks_exact(HASH_BYTE_SIZE)
+            .map(|chunk| <[u8; HASH_BYTE_SIZE]>::try_from(chunk).unwrap());
+
+        let saturated_name_hash = chunks.next().unwrap();
+        let content_key = Key::new(chunks.next().unwrap()).into();
+        let ratchet_key = Key::new(chunks.next().unwrap()).into();
+
+        let mut cursor = Cursor::new(cid_bytes);
+        let forest_cid = Cid::read_bytes(&mut cursor)
+            .map_err(|err| FsError::InvalidPrivateLink(format!("Invalid forest CID: {err}")))?;
+
+        let trailing = cid_bytes.len() - cursor.position() as usize;
+        ensure!(
+            trailing == 0,
+            FsError::InvalidPrivateLink(format!(
+                "Expected no bytes after the forest CID, got {trailing}"
+            ))
+        );
+
+        Ok(Self {
+            private_ref: PrivateRef {
+                saturated_name_hash,
+                content_key,
+                ratchet_key,
+                cipher_suite,
+            },
+            forest_cid,
+        })
+    }
+}
+
+impl fmt::Display for PrivateLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", multibase::encode(Base::Base58Btc, self.to_bytes()))
+    }
+}
+
+impl FromStr for PrivateLink {
+    type Err = anyhow::Error;
+
+    /// Parses a private link from its multibase string. Any multibase base is accepted.
+    fn from_str(s: &str) -> Result<Self> {
+        let (_, bytes) = multibase::decode(s)
+            .map_err(|err| FsError::InvalidPrivateLink(format!("Invalid multibase: {err}")))?;
+
+        Self::from_bytes(&bytes)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod private_link_tests {
+    use std::rc::Rc;
+
+    use chrono::Utc;
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use test_log::test;
+
+    use super::*;
+    use crate::{
+        private::PrivateForest, BlockStore, MemoryBlockStore, Namefilter, PrivateDirectory,
+        PrivateOpResult,
+    };
+
+    #[test(async_std::test)]
+    async fn parsed_private_link_opens_directory() {
+        let store = &mut MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let root_dir = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            rng,
+        ));
+
+        let PrivateOpResult { root_dir, hamt, .. } = root_dir
+            .mkdir(
+                &["pictures".into()],
+                true,
+                Utc::now(),
+                Rc::new(PrivateForest::new()),
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+        let forest_cid = store.put_async_serializable(&hamt).await.unwrap();
+        let link = PrivateLink::new(root_dir.header.get_private_ref().unwrap(), forest_cid);
+
+        let parsed = link.to_string().parse::<PrivateLink>().unwrap();
+        let forest = store
+            .get_deserializable::<PrivateForest>(&parsed.forest_cid)
+            .await
+            .unwrap();
+        let node = forest
+            .get(&parsed.private_ref, store)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(node.as_dir().unwrap(), root_dir);
+    }
+
+    #[test]
+    fn invalid_private_links_are_rejected() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let dir = PrivateDirectory::new(Namefilter::default(), Utc::now(), rng);
+        let bytes =
+            PrivateLink::new(dir.header.get_private_ref().unwrap(), Cid::default()).to_bytes();
+
+        let mut wrong_type = bytes.clone();
+        wrong_type[0] = 0;
+        let mut wrong_version = bytes.clone();
+        wrong_version[1] = PRIVATE_LINK_VERSION + 1;
+        let mut wrong_cid = bytes.clone();
+        wrong_cid.truncate(PRIVATE_LINK_HEADER_SIZE + 2);
+
+        for bytes in [wrong_type, wrong_version, wrong_cid, bytes[..10].to_vec()] {
+            assert!(PrivateLink::from_bytes(&bytes).is_err());
+        }
+
+        assert!("not a link".parse::<PrivateLink>().is_err());
+    }
+
+    #[test]
+    fn private_links_with_trailing_bytes_are_rejected() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let dir = PrivateDirectory::new(Namefilter::default(), Utc::now(), rng);
+        let mut bytes =
+            PrivateLink::new(dir.header.get_private_ref().unwrap(), Cid::default()).to_bytes();
+        bytes.push(0);
+
+        let error = PrivateLink::from_bytes(&bytes).unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<FsError>(),
+            Some(FsError::InvalidPrivateLink(message)) if message.contains("after the forest CID")
+        ));
+    }
+
+    #[test]
+    fn version_one_private_links_can_be_decoded() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let dir = PrivateDirectory::new(Namefilter::default(), Utc::now(), rng);
+        let link = PrivateLink::new(dir.header.get_private_ref().unwrap(), Cid::default());
+
+        // Version 1 had no cipher suite byte.
+        let mut bytes = link.to_bytes();
+        bytes[1] = 1;
+        bytes.remove(2);
+
+        assert_eq!(PrivateLink::from_bytes(&bytes).unwrap(), link);
+
+        let mut private_ref = link.private_ref.clone();
+        private_ref.cipher_suite = CipherSuite::XChaCha20Poly1305;
+        let link = PrivateLink::new(private_ref, Cid::default());
+
+        assert_eq!(PrivateLink::from_bytes(&link.to_bytes()).unwrap(), link);
+    }
+}
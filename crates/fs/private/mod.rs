@@ -1,15 +1,25 @@
+mod diff;
 mod directory;
 mod file;
 mod forest;
 pub mod hamt;
+mod history;
 mod key;
+mod keychain;
+mod link;
 pub mod namefilter;
 mod node;
+mod password;
+pub mod share;
 
 pub use directory::*;
 pub use file::*;
 pub use forest::*;
 pub use hamt::*;
 pub use key::*;
+pub use keychain::*;
+pub use link::*;
 pub use namefilter::*;
 pub use node::*;
+pub use password::*;
+pub use share::{ExchangeKey, PrivateKey, X25519ExchangeKey, X25519PrivateKey};
@@ -12,11 +12,13 @@ use rand_core::RngCore;
 use serde::{Deserialize, Serialize};
 use sha3::Sha3_256;
 use skip_ratchet::{seek::JumpSize, Ratchet, RatchetSeeker};
+use zeroize::Zeroizing;
 
 use crate::{utils, BlockStore, FsError, HashOutput, Id, NodeType, HASH_BYTE_SIZE};
 
 use super::{
-    hamt::Hasher, namefilter::Namefilter, Key, PrivateDirectory, PrivateFile, PrivateForest,
+    hamt::Hasher, namefilter::Namefilter, CipherSuite, Key, PrivateDirectory,
+    PrivateDirectorySnapshot, PrivateFile, PrivateFileSnapshot, PrivateForest,
 };
 
 //--------------------------------------------------------------------------------------------------
@@ -74,7 +76,6 @@ pub struct RatchetKey(pub Key);
 /// let file = PrivateFile::new(
 ///     Namefilter::default(),
 ///     Utc::now(),
-///     b"hello world".to_vec(),
 ///     rng,
 /// );
 ///
@@ -88,10 +89,16 @@ pub struct PrivateNodeHeader {
     pub(crate) ratchet: Ratchet,
     /// Used for ancestry checks and as a key fot the HAMT.
     pub(crate) bare_name: Namefilter,
+    /// The cipher suite the node is encrypted with. It is stored next to the encrypted header, as
+    /// it is needed to decrypt it.
+    #[serde(skip)]
+    pub(crate) cipher_suite: CipherSuite,
 }
 
 /// PrivateRef holds the information to fetch associated node from a HAMT and decrypt it if it is present.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Its keys are zeroed on drop and hidden from the `Debug` output.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PrivateRef {
     /// Sha3-256 hash of saturated namefilter.
     pub(crate) saturated_name_hash: HashOutput,
@@ -99,6 +106,43 @@ pub struct PrivateRef {
     pub(crate) content_key: ContentKey,
     /// Skip-ratchet-derived key.
     pub(crate) ratchet_key: RatchetKey,
+    /// The cipher suite the node is encrypted with.
+    #[serde(default, skip_serializing_if = "CipherSuite::is_default")]
+    pub(crate) cipher_suite: CipherSuite,
+}
+
+/// SnapshotRef holds the information to fetch a single revision of a node from a HAMT and decrypt
+/// its content. Unlike a `PrivateRef`, it has no ratchet key, so later revisions of the node
+/// cannot be found with it.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotRef {
+    /// Sha3-256 hash of saturated namefilter.
+    pub(crate) saturated_name_hash: HashOutput,
+    /// Sha3-256 hash of the ratchet key.
+    pub(crate) content_key: ContentKey,
+    /// The cipher suite the node is encrypted with.
+    #[serde(default, skip_serializing_if = "CipherSuite::is_default")]
+    pub(crate) cipher_suite: CipherSuite,
+}
+
+/// The form a private ref is stored in inside a directory. The ratchet key is encrypted with the
+/// ratchet key of the directory, so holders of a snapshot of the directory cannot follow later
+/// revisions of its entries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PrivateRefSerde {
+    pub(crate) saturated_name_hash: HashOutput,
+    pub(crate) content_key: ContentKey,
+    pub(crate) ratchet_key: Vec<u8>,
+    #[serde(default, skip_serializing_if = "CipherSuite::is_default")]
+    pub(crate) cipher_suite: CipherSuite,
+}
+
+/// A single revision of a node in the WNFS private file system, decrypted with a snapshot ref.
+/// It has the metadata and content of the node but not its header.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrivateSnapshot {
+    File(Rc<PrivateFileSnapshot>),
+    Dir(Rc<PrivateDirectorySnapshot>),
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -222,6 +266,64 @@ impl PrivateNode {
         .await
     }
 
+    /// Resets the ratchets of the private sub tree, so every node in it is stored under fresh keys.
+    /// The bare names are kept.
+    #[async_recursion(?Send)]
+    pub(crate) async fn rotate_keys<B, R>(
+        &mut self,
+        hamt: Rc<PrivateForest>,
+        store: &mut B,
+        rng: &mut R,
+    ) -> Result<Rc<PrivateForest>>
+    where
+        B: BlockStore,
+        R: RngCore,
+    {
+        let hamt = match self {
+            Self::File(file) => {
+                let mut file = (**file).clone();
+                file.header.reset_ratchet(rng);
+
+                *self = Self::File(Rc::new(file));
+
+                hamt
+            }
+            Self::Dir(old_dir) => {
+                let mut dir = (**old_dir).clone();
+
+                let mut working_hamt = Rc::clone(&hamt);
+                for (name, private_ref) in &old_dir.entries {
+                    let mut node = hamt
+                        .get(private_ref, store)
+                        .await?
+                        .ok_or(FsError::NotFound)?;
+
+                    working_hamt = node.rotate_keys(working_hamt, store, rng).await?;
+
+                    dir.entries
+                        .insert(name.clone(), node.get_header().get_private_ref()?);
+                }
+
+                dir.header.reset_ratchet(rng);
+
+                *self = Self::Dir(Rc::new(dir));
+
+                working_hamt
+            }
+        };
+
+        let header = self.get_header();
+
+        hamt.set(
+            header.get_saturated_name(),
+            &header.get_private_ref()?,
+            self,
+            store,
+            rng,
+        )
+        .await
+    }
+
     /// Gets the header of the node.
     ///
     /// # Examples
@@ -290,7 +392,6 @@ impl PrivateNode {
     /// let file = Rc::new(PrivateFile::new(
     ///     Namefilter::default(),
     ///     Utc::now(),
-    ///     b"hello world".to_vec(),
     ///     rng,
     /// ));
     /// let node = PrivateNode::File(Rc::clone(&file));
@@ -342,7 +443,6 @@ impl PrivateNode {
     /// let file = Rc::new(PrivateFile::new(
     ///     Namefilter::default(),
     ///     Utc::now(),
-    ///     b"hello world".to_vec(),
     ///     rng,
     /// ));
     /// let node = PrivateNode::File(file);
@@ -354,7 +454,51 @@ impl PrivateNode {
     }
 
     /// Gets the latest version of the node using exponential search.
-    pub(crate) async fn search_latest<B: BlockStore>(
+    ///
+    /// If the revision of this node is not in the forest, the node itself is returned. This is
+    /// useful to get back to the latest revision of a directory recomputed with
+    /// [`PrivateDirectory::with_seed`], provided its first revision was stored in the forest.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    /// use wnfs::{
+    ///     private::PrivateForest, MemoryBlockStore, Namefilter, PrivateDirectory, PrivateNode,
+    ///     PrivateOpResult,
+    /// };
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let rng = &mut thread_rng();
+    ///     let dir = Rc::new(PrivateDirectory::new(Namefilter::default(), Utc::now(), rng));
+    ///     let node = PrivateNode::Dir(Rc::clone(&dir));
+    ///     let hamt = Rc::new(PrivateForest::new())
+    ///         .set(
+    ///             dir.header.get_saturated_name(),
+    ///             &dir.header.get_private_ref().unwrap(),
+    ///             &node,
+    ///             store,
+    ///             rng,
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let PrivateOpResult { root_dir, hamt, .. } = dir
+    ///         .mkdir(&["pictures".into()], true, Utc::now(), hamt, store, rng)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let latest = node.search_latest(&hamt, store).await.unwrap();
+    ///
+    ///     assert_eq!(latest.as_dir().unwrap(), root_dir);
+    /// }
+    /// ```
+    pub async fn search_latest<B: BlockStore>(
         &self,
         forest: &PrivateForest,
         store: &B,
@@ -491,6 +635,23 @@ impl PrivateNodeHeader {
             },
             ratchet: Ratchet::zero(ratchet_seed),
             inumber,
+            cipher_suite: CipherSuite::default(),
+        }
+    }
+
+    /// Creates a new PrivateNodeHeader with the inumber and ratchet derived from a seed.
+    pub(crate) fn with_seed(parent_bare_name: Namefilter, seed: &HashOutput) -> Self {
+        let inumber = Sha3_256::hash(&[&seed[..], b"inumber"].concat());
+        let ratchet_seed = Sha3_256::hash(&[&seed[..], b"ratchet"].concat());
+        Self {
+            bare_name: {
+                let mut namefilter = parent_bare_name;
+                namefilter.add(&inumber);
+                namefilter
+            },
+            ratchet: Ratchet::zero(ratchet_seed),
+            inumber,
+            cipher_suite: CipherSuite::default(),
         }
     }
 
@@ -513,6 +674,54 @@ impl PrivateNodeHeader {
         self.ratchet = Ratchet::zero(utils::get_random_bytes(rng))
     }
 
+    /// Gets the ratchet of the node, which marks its revision.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{PrivateFile, Namefilter};
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// let rng = &mut thread_rng();
+    /// let file = PrivateFile::new(
+    ///     Namefilter::default(),
+    ///     Utc::now(),
+    ///     rng,
+    /// );
+    ///
+    /// let ratchet = file.header.get_ratchet();
+    ///
+    /// println!("Ratchet: {:?}", ratchet);
+    /// ```
+    #[inline]
+    pub fn get_ratchet(&self) -> &Ratchet {
+        &self.ratchet
+    }
+
+    /// Gets the cipher suite the node is encrypted with.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{private::CipherSuite, PrivateFile, Namefilter};
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// let rng = &mut thread_rng();
+    /// let file = PrivateFile::new(
+    ///     Namefilter::default(),
+    ///     Utc::now(),
+    ///     rng,
+    /// );
+    ///
+    /// assert_eq!(file.header.get_cipher_suite(), CipherSuite::Aes256Gcm);
+    /// ```
+    #[inline]
+    pub fn get_cipher_suite(&self) -> CipherSuite {
+        self.cipher_suite
+    }
+
     /// Gets the private ref of the current header.
     ///
     /// # Examples
@@ -526,7 +735,6 @@ impl PrivateNodeHeader {
     /// let file = PrivateFile::new(
     ///     Namefilter::default(),
     ///     Utc::now(),
-    ///     b"hello world".to_vec(),
     ///     rng,
     /// );
     ///
@@ -542,6 +750,7 @@ impl PrivateNodeHeader {
             saturated_name_hash,
             content_key: Key::new(Sha3_256::hash(&ratchet_key.as_bytes())).into(),
             ratchet_key: ratchet_key.into(),
+            cipher_suite: self.cipher_suite,
         })
     }
 
@@ -566,7 +775,6 @@ impl PrivateNodeHeader {
     /// let file = PrivateFile::new(
     ///     Namefilter::default(),
     ///     Utc::now(),
-    ///     b"hello world".to_vec(),
     ///     rng,
     /// );
     ///
@@ -581,6 +789,214 @@ impl PrivateNodeHeader {
     }
 }
 
+impl PrivateRef {
+    /// Gets a snapshot ref to the revision of the node this private ref points to. It can be
+    /// shared to give access to that revision alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{PrivateFile, Namefilter};
+    /// use chrono::Utc;
+    /// use rand::thread_rng;
+    ///
+    /// let rng = &mut thread_rng();
+    /// let file = PrivateFile::new(
+    ///     Namefilter::default(),
+    ///     Utc::now(),
+    ///     rng,
+    /// );
+    ///
+    /// let snapshot_ref = file.header.get_private_ref().unwrap().as_snapshot_ref();
+    ///
+    /// println!("Snapshot ref: {:?}", snapshot_ref);
+    /// ```
+    pub fn as_snapshot_ref(&self) -> SnapshotRef {
+        SnapshotRef {
+            saturated_name_hash: self.saturated_name_hash,
+            content_key: self.content_key.clone(),
+            cipher_suite: self.cipher_suite,
+        }
+    }
+
+    /// Encrypts the ratchet key with the ratchet key of the parent directory.
+    pub(crate) fn to_serde<R: RngCore>(
+        &self,
+        parent_ratchet_key: &RatchetKey,
+        rng: &mut R,
+    ) -> Result<PrivateRefSerde> {
+        Ok(PrivateRefSerde {
+            saturated_name_hash: self.saturated_name_hash,
+            content_key: self.content_key.clone(),
+            ratchet_key: self.cipher_suite.encrypt(
+                &parent_ratchet_key.0,
+                self.ratchet_key.0.as_bytes(),
+                rng,
+            )?,
+            cipher_suite: self.cipher_suite,
+        })
+    }
+
+    /// Decrypts the ratchet key with the ratchet key of the parent directory.
+    pub(crate) fn from_serde(
+        private_ref: PrivateRefSerde,
+        parent_ratchet_key: &RatchetKey,
+    ) -> Result<Self> {
+        let ratchet_key = Zeroizing::new(
+            private_ref
+                .cipher_suite
+                .decrypt(&parent_ratchet_key.0, &private_ref.ratchet_key)?,
+        );
+
+        Self::with_ratchet_key(private_ref, &ratchet_key)
+    }
+
+    /// Reads a private ref stored by a directory from before version 0.3.0, which kept the
+    /// ratchet keys of its entries unencrypted.
+    pub(crate) fn from_legacy_serde(private_ref: PrivateRefSerde) -> Result<Self> {
+        let ratchet_key = Zeroizing::new(private_ref.ratchet_key.clone());
+        Self::with_ratchet_key(private_ref, &ratchet_key)
+    }
+
+    fn with_ratchet_key(private_ref: PrivateRefSerde, ratchet_key: &[u8]) -> Result<Self> {
+        let ratchet_key = ratchet_key.try_into().map_err(|_| {
+            FsError::InvalidDeserialization("Ratchet key must be 32 bytes long".into())
+        })?;
+
+        Ok(Self {
+            saturated_name_hash: private_ref.saturated_name_hash,
+            content_key: private_ref.content_key,
+            ratchet_key: Key::new(ratchet_key).into(),
+            cipher_suite: private_ref.cipher_suite,
+        })
+    }
+}
+
+impl From<PrivateRefSerde> for SnapshotRef {
+    fn from(private_ref: PrivateRefSerde) -> Self {
+        Self {
+            saturated_name_hash: private_ref.saturated_name_hash,
+            content_key: private_ref.content_key,
+            cipher_suite: private_ref.cipher_suite,
+        }
+    }
+}
+
+impl Debug for PrivateRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrivateRef")
+            .field("saturated_name_hash", &self.saturated_name_hash)
+            .field("content_key", &"<hidden>")
+            .field("ratchet_key", &"<hidden>")
+            .field("cipher_suite", &self.cipher_suite)
+            .finish()
+    }
+}
+
+impl Debug for SnapshotRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SnapshotRef")
+            .field("saturated_name_hash", &self.saturated_name_hash)
+            .field("content_key", &"<hidden>")
+            .field("cipher_suite", &self.cipher_suite)
+            .finish()
+    }
+}
+
+impl PrivateSnapshot {
+    /// Casts a snapshot to a directory snapshot.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::{collections::BTreeMap, rc::Rc};
+    ///
+    /// use chrono::Utc;
+    /// use semver::Version;
+    /// use wnfs::{
+    ///     private::{PrivateDirectorySnapshot, PrivateSnapshot},
+    ///     Metadata,
+    /// };
+    ///
+    /// let dir = Rc::new(PrivateDirectorySnapshot {
+    ///     version: Version::new(0, 3, 0),
+    ///     metadata: Metadata::new(Utc::now()),
+    ///     entries: BTreeMap::new(),
+    /// });
+    /// let snapshot = PrivateSnapshot::Dir(Rc::clone(&dir));
+    ///
+    /// assert_eq!(snapshot.as_dir().unwrap(), dir);
+    /// ```
+    pub fn as_dir(&self) -> Result<Rc<PrivateDirectorySnapshot>> {
+        Ok(match self {
+            Self::Dir(dir) => Rc::clone(dir),
+            _ => bail!(FsError::NotADirectory),
+        })
+    }
+
+    /// Casts a snapshot to a file snapshot.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use semver::Version;
+    /// use wnfs::{
+    ///     private::{FileContent, PrivateFileSnapshot, PrivateSnapshot},
+    ///     Metadata,
+    /// };
+    ///
+    /// let file = Rc::new(PrivateFileSnapshot {
+    ///     version: Version::new(0, 3, 0),
+    ///     metadata: Metadata::new(Utc::now()),
+    ///     content: FileContent::Inline { data: vec![] },
+    /// });
+    /// let snapshot = PrivateSnapshot::File(Rc::clone(&file));
+    ///
+    /// assert_eq!(snapshot.as_file().unwrap(), file);
+    /// ```
+    pub fn as_file(&self) -> Result<Rc<PrivateFileSnapshot>> {
+        Ok(match self {
+            Self::File(file) => Rc::clone(file),
+            _ => bail!(FsError::NotAFile),
+        })
+    }
+
+    /// Deserializes the snapshot from dag-cbor bytes.
+    pub(crate) fn deserialize_from_cbor(bytes: &[u8]) -> Result<Self> {
+        let ipld = Ipld::decode(DagCborCodec, &mut Cursor::new(bytes))?;
+        ipld.try_into()
+    }
+}
+
+impl TryFrom<Ipld> for PrivateSnapshot {
+    type Error = anyhow::Error;
+
+    fn try_from(ipld: Ipld) -> Result<Self> {
+        match ipld {
+            Ipld::Map(map) => {
+                let r#type: NodeType = map
+                    .get("type")
+                    .ok_or(FsError::MissingNodeType)?
+                    .try_into()?;
+
+                Ok(match r#type {
+                    NodeType::PrivateFile => {
+                        Self::File(Rc::new(PrivateFileSnapshot::deserialize(Ipld::Map(map))?))
+                    }
+                    NodeType::PrivateDirectory => Self::Dir(Rc::new(
+                        PrivateDirectorySnapshot::deserialize(Ipld::Map(map))?,
+                    )),
+                    other => bail!(FsError::UnexpectedNodeType(other)),
+                })
+            }
+            other => bail!("Expected `Ipld::Map` got {:#?}", other),
+        }
+    }
+}
+
 impl From<Key> for RatchetKey {
     fn from(key: Key) -> Self {
         Self(key)
@@ -612,18 +1028,42 @@ impl From<ContentKey> for Key {
 #[cfg(test)]
 mod private_node_tests {
     use proptest::test_runner::{RngAlgorithm, TestRng};
+    use test_log::test;
 
     use super::*;
+    use crate::MemoryBlockStore;
 
     #[test]
-    fn serialized_private_node_can_be_deserialized() {
+    fn private_ref_debug_output_hides_keys() {
+        let private_ref = PrivateRef {
+            saturated_name_hash: [1; 32],
+            content_key: Key::new([0xAB; 32]).into(),
+            ratchet_key: Key::new([0xCD; 32]).into(),
+            cipher_suite: CipherSuite::default(),
+        };
+
+        let output = format!("{private_ref:?} {:?}", private_ref.as_snapshot_ref());
+
+        assert!(output.contains("<hidden>"));
+        assert!(!output.contains("171") && !output.contains("205"));
+    }
+
+    #[test(async_std::test)]
+    async fn serialized_private_node_can_be_deserialized() {
         let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
-        let original_file = PrivateNode::File(Rc::new(PrivateFile::new(
+        let store = &mut MemoryBlockStore::default();
+        let hamt = Rc::new(PrivateForest::new());
+        let (file, _) = PrivateFile::with_content(
             Namefilter::default(),
             Utc::now(),
             b"Lorem ipsum dolor sit amet".to_vec(),
+            hamt,
+            store,
             rng,
-        )));
+        )
+        .await
+        .unwrap();
+        let original_file = PrivateNode::File(Rc::new(file));
         let private_ref = original_file.get_header().get_private_ref().unwrap();
 
         let bytes = original_file.serialize_to_cbor(rng).unwrap();
@@ -0,0 +1,245 @@
+//! Keys derived from passwords.
+
+use anyhow::{bail, Result};
+use argon2::{Algorithm, Argon2, Params};
+use libipld::Cid;
+use rand_core::RngCore;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::{utils, BlockStore, FsError};
+
+use super::Key;
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The version of the password header format written by [`PasswordHeader::store`].
+pub const PASSWORD_HEADER_VERSION: Version = Version::new(0, 1, 0);
+
+/// The number of bytes in the salt generated by [`PasswordHeader::new`].
+pub const PASSWORD_SALT_SIZE: usize = 16;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The cost parameters of Argon2id, the function keys are derived from passwords with.
+///
+/// Higher costs make guessing passwords slower, but also make deriving a key slower.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PasswordParams {
+    /// The memory size in KiB.
+    pub memory_cost: u32,
+    /// The number of passes over the memory.
+    pub time_cost: u32,
+    /// The number of lanes.
+    pub parallelism: u32,
+}
+
+/// The salt and parameters a key was derived from a password with.
+///
+/// The header is not secret and is stored unencrypted, so another device can derive the same key
+/// from the same password, e.g. to decrypt a [`Keychain`](super::Keychain) with it.
+///
+/// # Examples
+///
+/// ```
+/// use rand::thread_rng;
+/// use wnfs::{private::PasswordHeader, MemoryBlockStore};
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &mut MemoryBlockStore::default();
+///     let rng = &mut thread_rng();
+///
+///     let header = PasswordHeader::new(rng);
+///     let key = header.derive_key(b"correct horse battery staple").unwrap();
+///     let cid = header.store(store).await.unwrap();
+///
+///     // On another device.
+///     let header = PasswordHeader::load(&cid, store).await.unwrap();
+///
+///     assert_eq!(header.derive_key(b"correct horse battery staple").unwrap(), key);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PasswordHeader {
+    pub salt: Vec<u8>,
+    pub params: PasswordParams,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PasswordHeaderSerializable {
+    version: Version,
+    salt: Vec<u8>,
+    params: PasswordParams,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl Key {
+    /// Derives a key from a password with Argon2id.
+    ///
+    /// The same password, salt and parameters always derive the same key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::private::{Key, PasswordParams};
+    ///
+    /// let params = PasswordParams::default();
+    /// let key = Key::derive_from_password(b"hunter2", b"some unique salt", &params).unwrap();
+    ///
+    /// assert_eq!(
+    ///     Key::derive_from_password(b"hunter2", b"some unique salt", &params).unwrap(),
+    ///     key
+    /// );
+    /// ```
+    pub fn derive_from_password(
+        password: &[u8],
+        salt: &[u8],
+        params: &PasswordParams,
+    ) -> Result<Self> {
+        let params = Params::new(
+            params.memory_cost,
+            params.time_cost,
+            params.parallelism,
+            None,
+        )
+        .map_err(|e| FsError::UnableToDeriveKey(e.to_string()))?;
+
+        // Derive into the key itself, so no copy of the bytes is left behind.
+        let mut key = Self([0; 32]);
+        Argon2::new(Algorithm::Argon2id, argon2::Version::V0x13, params)
+            .hash_password_into(password, salt, &mut key.0)
+            .map_err(|e| FsError::UnableToDeriveKey(e.to_string()))?;
+
+        Ok(key)
+    }
+}
+
+impl Default for PasswordParams {
+    /// The parameters recommended by OWASP for Argon2id: 19 MiB of memory, 2 passes and 1 lane.
+    fn default() -> Self {
+        Self {
+            memory_cost: Params::DEFAULT_M_COST,
+            time_cost: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl PasswordHeader {
+    /// Creates a header with a random salt and the default parameters.
+    pub fn new(rng: &mut impl RngCore) -> Self {
+        Self::with_params(PasswordParams::default(), rng)
+    }
+
+    /// Creates a header with a random salt and the given parameters.
+    pub fn with_params(params: PasswordParams, rng: &mut impl RngCore) -> Self {
+        Self {
+            salt: utils::get_random_bytes::<PASSWORD_SALT_SIZE>(rng).to_vec(),
+            params,
+        }
+    }
+
+    /// Derives the key of a password with the salt and parameters of this header.
+    pub fn derive_key(&self, password: &[u8]) -> Result<Key> {
+        Key::derive_from_password(password, &self.salt, &self.params)
+    }
+
+    /// Stores the header in the block store.
+    pub async fn store<B: BlockStore>(&self, store: &mut B) -> Result<Cid> {
+        let serializable = PasswordHeaderSerializable {
+            version: PASSWORD_HEADER_VERSION,
+            salt: self.salt.clone(),
+            params: self.params,
+        };
+
+        store.put_serializable(&serializable).await
+    }
+
+    /// Loads a header stored with [`PasswordHeader::store`].
+    pub async fn load<B: BlockStore>(cid: &Cid, store: &B) -> Result<Self> {
+        let PasswordHeaderSerializable {
+            version,
+            salt,
+            params,
+        } = store.get_deserializable(cid).await?;
+
+        if version.major != PASSWORD_HEADER_VERSION.major
+            || version.minor != PASSWORD_HEADER_VERSION.minor
+        {
+            bail!(FsError::UnsupportedPasswordHeaderVersion(version));
+        }
+
+        Ok(Self { salt, params })
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod password_tests {
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use test_log::test;
+
+    use super::*;
+    use crate::MemoryBlockStore;
+
+    /// Cheap parameters to keep the tests fast.
+    const TEST_PARAMS: PasswordParams = PasswordParams {
+        memory_cost: 64,
+        time_cost: 1,
+        parallelism: 1,
+    };
+
+    #[test]
+    fn keys_depend_on_password_salt_and_params() {
+        let key = Key::derive_from_password(b"password", b"saltsaltsalt", &TEST_PARAMS).unwrap();
+
+        assert_eq!(
+            Key::derive_from_password(b"password", b"saltsaltsalt", &TEST_PARAMS).unwrap(),
+            key
+        );
+        assert_ne!(
+            Key::derive_from_password(b"Password", b"saltsaltsalt", &TEST_PARAMS).unwrap(),
+            key
+        );
+        assert_ne!(
+            Key::derive_from_password(b"password", b"saltsaltsalT", &TEST_PARAMS).unwrap(),
+            key
+        );
+
+        let params = PasswordParams {
+            time_cost: 2,
+            ..TEST_PARAMS
+        };
+
+        assert_ne!(
+            Key::derive_from_password(b"password", b"saltsaltsalt", &params).unwrap(),
+            key
+        );
+        assert!(Key::derive_from_password(b"password", b"salt", &TEST_PARAMS).is_err());
+    }
+
+    #[test(async_std::test)]
+    async fn stored_header_derives_the_same_key() {
+        let store = &mut MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let header = PasswordHeader::with_params(TEST_PARAMS, rng);
+        let key = header.derive_key(b"password").unwrap();
+
+        let cid = header.store(store).await.unwrap();
+        let loaded = PasswordHeader::load(&cid, store).await.unwrap();
+
+        assert_eq!(loaded, header);
+        assert_eq!(loaded.derive_key(b"password").unwrap(), key);
+    }
+}
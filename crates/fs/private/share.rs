@@ -0,0 +1,441 @@
+//! Sharing of private refs with other users.
+//!
+//! A private ref is shared by encrypting it to the exchange key of the recipient and storing it in
+//! the private forest under a label derived from the sender, the recipient and a counter. The
+//! recipient finds the shares addressed to them by checking the labels with increasing counters
+//! until one is missing.
+
+use std::rc::Rc;
+
+use anyhow::{ensure, Result};
+use async_trait::async_trait;
+use libipld::IpldCodec;
+use rand_core::RngCore;
+use sha3::Sha3_256;
+use x25519_dalek::{PublicKey, SharedSecret, StaticSecret};
+
+use crate::{dagcbor, utils, BlockStore, FsError};
+
+use super::{hamt::Hasher, namefilter::Namefilter, Key, PrivateForest, PrivateRef};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The size of an X25519 public key in bytes.
+pub const X25519_KEY_SIZE: usize = 32;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The public half of an asymmetric key pair that shares are encrypted to.
+///
+/// This can be implemented for RSA-OAEP keys, e.g. ones held by the WebCrypto API.
+#[async_trait(?Send)]
+pub trait ExchangeKey {
+    /// Gets the bytes that identify the key. They are used in the labels of shares addressed to
+    /// the key.
+    fn to_bytes(&self) -> Vec<u8>;
+
+    /// Encrypts the data so that only the holder of the matching private key can decrypt it.
+    async fn encrypt<R: RngCore>(&self, data: &[u8], rng: &mut R) -> Result<Vec<u8>>;
+}
+
+/// The private half of an asymmetric key pair that shares are decrypted with.
+#[async_trait(?Send)]
+pub trait PrivateKey {
+    /// Decrypts data that was encrypted to the matching exchange key.
+    async fn decrypt(&self, cipher_text: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// An X25519 public key that shares can be encrypted to.
+///
+/// # Examples
+///
+/// ```
+/// use rand::thread_rng;
+/// use wnfs::private::X25519PrivateKey;
+///
+/// let private_key = X25519PrivateKey::new(&mut thread_rng());
+/// let exchange_key = private_key.get_exchange_key();
+///
+/// println!("Exchange key: {:?}", exchange_key);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct X25519ExchangeKey(PublicKey);
+
+/// An X25519 private key that shares can be decrypted with.
+///
+/// Data is encrypted to it with an ephemeral key pair. The key for the encryption is derived from
+/// the secret shared between the ephemeral key pair and this one.
+#[derive(Clone)]
+pub struct X25519PrivateKey(StaticSecret);
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl X25519ExchangeKey {
+    /// Creates an exchange key from its bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rand::thread_rng;
+    /// use wnfs::private::{ExchangeKey, X25519ExchangeKey, X25519PrivateKey};
+    ///
+    /// let exchange_key = X25519PrivateKey::new(&mut thread_rng()).get_exchange_key();
+    /// let bytes = exchange_key.to_bytes();
+    ///
+    /// assert_eq!(X25519ExchangeKey::from_bytes(&bytes).unwrap(), exchange_key);
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let bytes: [u8; X25519_KEY_SIZE] = bytes
+            .try_into()
+            .map_err(|_| FsError::InvalidExchangeKey(bytes.len()))?;
+
+        Ok(Self(PublicKey::from(bytes)))
+    }
+}
+
+#[async_trait(?Send)]
+impl ExchangeKey for X25519ExchangeKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.as_bytes().to_vec()
+    }
+
+    async fn encrypt<R: RngCore>(&self, data: &[u8], rng: &mut R) -> Result<Vec<u8>> {
+        let ephemeral_secret = StaticSecret::from(utils::get_random_bytes::<32>(rng));
+        let ephemeral_public = PublicKey::from(&ephemeral_secret);
+        let shared_secret = ephemeral_secret.diffie_hellman(&self.0);
+        let key = derive_shared_key(&shared_secret, &ephemeral_public, &self.0);
+
+        let cipher_text = key.encrypt(&Key::generate_nonce(rng), data)?;
+        Ok([ephemeral_public.as_bytes().as_slice(), &cipher_text].concat())
+    }
+}
+
+impl X25519PrivateKey {
+    /// Generates a new private key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rand::thread_rng;
+    /// use wnfs::private::X25519PrivateKey;
+    ///
+    /// let private_key = X25519PrivateKey::new(&mut thread_rng());
+    /// ```
+    pub fn new<R: RngCore>(rng: &mut R) -> Self {
+        Self(StaticSecret::from(utils::get_random_bytes::<32>(rng)))
+    }
+
+    /// Creates a private key from its bytes.
+    pub fn from_bytes(bytes: [u8; X25519_KEY_SIZE]) -> Self {
+        Self(StaticSecret::from(bytes))
+    }
+
+    /// Gets the bytes of the private key.
+    pub fn to_bytes(&self) -> [u8; X25519_KEY_SIZE] {
+        self.0.to_bytes()
+    }
+
+    /// Gets the exchange key that matches this private key.
+    pub fn get_exchange_key(&self) -> X25519ExchangeKey {
+        X25519ExchangeKey(PublicKey::from(&self.0))
+    }
+}
+
+#[async_trait(?Send)]
+impl PrivateKey for X25519PrivateKey {
+    async fn decrypt(&self, cipher_text: &[u8]) -> Result<Vec<u8>> {
+        ensure!(
+            cipher_text.len() > X25519_KEY_SIZE,
+            FsError::UnableToDecrypt("Missing ephemeral key".into())
+        );
+
+        let (ephemeral_public, cipher_text) = cipher_text.split_at(X25519_KEY_SIZE);
+        let ephemeral_public = X25519ExchangeKey::from_bytes(ephemeral_public)?.0;
+        let shared_secret = self.0.diffie_hellman(&ephemeral_public);
+        let key = derive_shared_key(&shared_secret, &ephemeral_public, &PublicKey::from(&self.0));
+
+        key.decrypt(cipher_text)
+    }
+}
+
+impl std::fmt::Debug for X25519PrivateKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("X25519PrivateKey")
+            .field(&"<hidden>")
+            .finish()
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Shares a private ref with the holder of the recipient's exchange key. The private ref is
+/// encrypted to the exchange key and stored in the forest under the first unused share label for
+/// the sender and recipient.
+///
+/// The sender can be any bytes that identify the sending user, e.g. their own exchange key.
+///
+/// # Examples
+///
+/// ```
+/// use std::rc::Rc;
+///
+/// use chrono::Utc;
+/// use rand::thread_rng;
+///
+/// use wnfs::{
+///     private::{share, PrivateForest, X25519PrivateKey, ExchangeKey},
+///     MemoryBlockStore, Namefilter, PrivateDirectory,
+/// };
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &mut MemoryBlockStore::default();
+///     let rng = &mut thread_rng();
+///     let forest = Rc::new(PrivateForest::new());
+///     let dir = PrivateDirectory::new(Namefilter::default(), Utc::now(), rng);
+///     let private_ref = dir.header.get_private_ref().unwrap();
+///
+///     let sender = X25519PrivateKey::new(rng).get_exchange_key().to_bytes();
+///     let recipient_key = X25519PrivateKey::new(rng);
+///
+///     let forest = share::share(
+///         &private_ref,
+///         &sender,
+///         &recipient_key.get_exchange_key(),
+///         forest,
+///         store,
+///         rng,
+///     )
+///     .await
+///     .unwrap();
+///
+///     let shares = share::receive_shares(
+///         &sender,
+///         &recipient_key.get_exchange_key(),
+///         &recipient_key,
+///         &forest,
+///         store,
+///     )
+///     .await
+///     .unwrap();
+///
+///     assert_eq!(shares, vec![private_ref]);
+/// }
+/// ```
+pub async fn share<K, B, R>(
+    private_ref: &PrivateRef,
+    sender: &[u8],
+    recipient_key: &K,
+    forest: Rc<PrivateForest>,
+    store: &mut B,
+    rng: &mut R,
+) -> Result<Rc<PrivateForest>>
+where
+    K: ExchangeKey,
+    B: BlockStore,
+    R: RngCore,
+{
+    let recipient = recipient_key.to_bytes();
+
+    let mut counter = 0;
+    let label = loop {
+        let label = create_share_label(sender, &recipient, counter);
+        if !forest
+            .has(&Sha3_256::hash(&label.as_bytes()), store)
+            .await?
+        {
+            break label;
+        }
+
+        counter += 1;
+    };
+
+    let cbor_bytes = dagcbor::encode(private_ref)?;
+    let enc_bytes = recipient_key.encrypt(&cbor_bytes, rng).await?;
+    let cid = store.put_block(enc_bytes, IpldCodec::Raw).await?;
+
+    forest.set_encrypted(label, cid, store).await
+}
+
+/// Gets the private refs that the sender shared with the recipient, in the order they were
+/// shared. The recipient is identified by their exchange key and decrypts the shares with the
+/// matching private key.
+pub async fn receive_shares<K, P, B>(
+    sender: &[u8],
+    recipient_key: &K,
+    private_key: &P,
+    forest: &PrivateForest,
+    store: &B,
+) -> Result<Vec<PrivateRef>>
+where
+    K: ExchangeKey,
+    P: PrivateKey,
+    B: BlockStore,
+{
+    let recipient = recipient_key.to_bytes();
+
+    let mut private_refs = Vec::new();
+    for counter in 0.. {
+        let label = create_share_label(sender, &recipient, counter);
+        let cids = match forest
+            .get_encrypted(&Sha3_256::hash(&label.as_bytes()), store)
+            .await?
+        {
+            Some(cids) => cids,
+            None => break,
+        };
+
+        for cid in cids {
+            let enc_bytes = store.get_block(cid).await?;
+            let cbor_bytes = private_key.decrypt(&enc_bytes).await?;
+            private_refs.push(dagcbor::decode(&cbor_bytes)?);
+        }
+    }
+
+    Ok(private_refs)
+}
+
+/// Creates the label of the share with the given counter from the sender to the recipient.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs::private::share;
+///
+/// let label = share::create_share_label(b"sender", b"recipient", 0);
+///
+/// assert_eq!(label, share::create_share_label(b"sender", b"recipient", 0));
+/// assert_ne!(label, share::create_share_label(b"sender", b"recipient", 1));
+/// ```
+pub fn create_share_label(sender: &[u8], recipient: &[u8], counter: u64) -> Namefilter {
+    let mut label = Namefilter::default();
+    label.add(&Sha3_256::hash(&sender));
+    label.add(&Sha3_256::hash(&recipient));
+    label.add(&Sha3_256::hash(
+        &[sender, recipient, &counter.to_le_bytes()].concat(),
+    ));
+    label.saturate();
+
+    label
+}
+
+/// Derives a symmetric key from the secret shared between an ephemeral and a recipient key pair.
+fn derive_shared_key(
+    shared_secret: &SharedSecret,
+    ephemeral_public: &PublicKey,
+    recipient_public: &PublicKey,
+) -> Key {
+    Key::new(Sha3_256::hash(
+        &[
+            shared_secret.as_bytes().as_slice(),
+            ephemeral_public.as_bytes(),
+            recipient_public.as_bytes(),
+        ]
+        .concat(),
+    ))
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod share_tests {
+    use chrono::Utc;
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use test_log::test;
+
+    use super::*;
+    use crate::{MemoryBlockStore, PrivateDirectory};
+
+    #[test(async_std::test)]
+    async fn x25519_encrypted_data_can_only_be_decrypted_by_recipient() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let recipient_key = X25519PrivateKey::new(rng);
+        let other_key = X25519PrivateKey::new(rng);
+
+        let cipher_text = recipient_key
+            .get_exchange_key()
+            .encrypt(b"hello world", rng)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            recipient_key.decrypt(&cipher_text).await.unwrap(),
+            b"hello world"
+        );
+        assert!(other_key.decrypt(&cipher_text).await.is_err());
+    }
+
+    #[test(async_std::test)]
+    async fn shares_are_only_received_by_their_recipient() {
+        let store = &mut MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let sender = X25519PrivateKey::new(rng).get_exchange_key().to_bytes();
+        let recipient_key = X25519PrivateKey::new(rng);
+        let other_key = X25519PrivateKey::new(rng);
+
+        let mut forest = Rc::new(PrivateForest::new());
+        let mut private_refs = vec![];
+        for _ in 0..3 {
+            let dir = PrivateDirectory::new(Namefilter::default(), Utc::now(), rng);
+            let private_ref = dir.header.get_private_ref().unwrap();
+
+            forest = share(
+                &private_ref,
+                &sender,
+                &recipient_key.get_exchange_key(),
+                forest,
+                store,
+                rng,
+            )
+            .await
+            .unwrap();
+
+            private_refs.push(private_ref);
+        }
+
+        let received = receive_shares(
+            &sender,
+            &recipient_key.get_exchange_key(),
+            &recipient_key,
+            &forest,
+            store,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(received, private_refs);
+
+        let received = receive_shares(
+            &sender,
+            &other_key.get_exchange_key(),
+            &other_key,
+            &forest,
+            store,
+        )
+        .await
+        .unwrap();
+
+        assert!(received.is_empty());
+
+        // A private key that does not match the exchange key cannot decrypt the shares.
+        assert!(receive_shares(
+            &sender,
+            &recipient_key.get_exchange_key(),
+            &other_key,
+            &forest,
+            store,
+        )
+        .await
+        .is_err());
+    }
+}
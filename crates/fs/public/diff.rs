@@ -0,0 +1,225 @@
+//! Diffs of public directories.
+
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use async_recursion::async_recursion;
+
+use crate::{BlockStore, ChangeType, NodeChange, NodeType};
+
+use super::{PublicDirectory, PublicNode};
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl PublicDirectory {
+    /// Gets the files and directories that differ between this directory and another version of
+    /// it. Entries that have the same CID in both versions are skipped without being loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{ChangeType, NodeType, PublicDirectory, PublicOpResult, MemoryBlockStore};
+    /// use libipld::cid::Cid;
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let dir = Rc::new(PublicDirectory::new(Utc::now()));
+    ///
+    ///     let PublicOpResult { root_dir, .. } = Rc::clone(&dir)
+    ///         .write(&["cats.png".into()], Cid::default(), Utc::now(), store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let changes = root_dir.diff(&dir, store).await.unwrap();
+    ///
+    ///     assert_eq!(changes.len(), 1);
+    ///     assert_eq!(changes[0].r#type, ChangeType::Add);
+    ///     assert_eq!(changes[0].path, vec!["cats.png".to_string()]);
+    ///     assert_eq!(changes[0].node_type, NodeType::PublicFile);
+    /// }
+    /// ```
+    pub async fn diff<B: BlockStore>(
+        &self,
+        other: &Self,
+        store: &mut B,
+    ) -> Result<Vec<NodeChange>> {
+        let mut changes = Vec::new();
+        self.diff_helper(other, &mut Vec::new(), &mut changes, store)
+            .await?;
+        Ok(changes)
+    }
+
+    #[async_recursion(?Send)]
+    async fn diff_helper<B>(
+        &self,
+        other: &Self,
+        path: &mut Vec<String>,
+        changes: &mut Vec<NodeChange>,
+        store: &mut B,
+    ) -> Result<()>
+    where
+        B: BlockStore,
+    {
+        let names = self
+            .userland
+            .keys()
+            .chain(other.userland.keys())
+            .collect::<BTreeSet<_>>();
+
+        for name in names {
+            path.push(name.clone());
+            match (self.userland.get(name), other.userland.get(name)) {
+                (Some(link), Some(other_link)) => {
+                    if !link.deep_eq(other_link, store).await? {
+                        let node = link.resolve_value(store).await?;
+                        let other_node = other_link.resolve_value(store).await?;
+                        match (node, other_node) {
+                            (PublicNode::Dir(dir), PublicNode::Dir(other_dir)) => {
+                                Self::push_change(ChangeType::Modify, path, node, changes);
+                                dir.diff_helper(other_dir, path, changes, store).await?;
+                            }
+                            (PublicNode::File(_), PublicNode::File(_)) => {
+                                Self::push_change(ChangeType::Modify, path, node, changes);
+                            }
+                            _ => {
+                                Self::push_change(ChangeType::Remove, path, other_node, changes);
+                                Self::push_change(ChangeType::Add, path, node, changes);
+                            }
+                        }
+                    }
+                }
+                (Some(link), None) => {
+                    let node = link.resolve_value(store).await?;
+                    Self::push_change(ChangeType::Add, path, node, changes);
+                }
+                (None, Some(other_link)) => {
+                    let other_node = other_link.resolve_value(store).await?;
+                    Self::push_change(ChangeType::Remove, path, other_node, changes);
+                }
+                (None, None) => unreachable!(),
+            }
+            path.pop();
+        }
+
+        Ok(())
+    }
+
+    fn push_change(
+        r#type: ChangeType,
+        path: &[String],
+        node: &PublicNode,
+        changes: &mut Vec<NodeChange>,
+    ) {
+        let node_type = match node {
+            PublicNode::File(_) => NodeType::PublicFile,
+            PublicNode::Dir(_) => NodeType::PublicDirectory,
+        };
+
+        changes.push(NodeChange {
+            r#type,
+            path: path.to_vec(),
+            node_type,
+        });
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod public_diff_tests {
+    use std::rc::Rc;
+
+    use chrono::Utc;
+    use libipld::Cid;
+
+    use super::*;
+    use crate::{MemoryBlockStore, PublicOpResult};
+
+    #[async_std::test]
+    async fn diff_reports_changes_in_nested_directories() {
+        let store = &mut MemoryBlockStore::default();
+        let content = store.put_serializable(&"content").await.unwrap();
+        let root = Rc::new(PublicDirectory::new(Utc::now()));
+
+        let mut other = root;
+        for path in [
+            vec!["pictures", "cats", "tabby.png"],
+            vec!["pictures", "dogs", "pug.png"],
+            vec!["notes.txt"],
+        ] {
+            let path = path.into_iter().map(String::from).collect::<Vec<_>>();
+            other = other
+                .write(&path, Cid::default(), Utc::now(), store)
+                .await
+                .unwrap()
+                .root_dir;
+        }
+
+        let PublicOpResult { root_dir: main, .. } = Rc::clone(&other)
+            .write(
+                &["pictures".into(), "cats".into(), "tabby.png".into()],
+                content,
+                Utc::now(),
+                store,
+            )
+            .await
+            .unwrap();
+
+        let PublicOpResult { root_dir: main, .. } =
+            main.rm(&["notes.txt".into()], store).await.unwrap();
+
+        let PublicOpResult { root_dir: main, .. } = main
+            .mkdir(&["music".into()], Utc::now(), store)
+            .await
+            .unwrap();
+
+        let changes = main
+            .diff(&other, store)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|change| (change.path.join("/"), change.r#type, change.node_type))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            changes,
+            vec![
+                ("music".into(), ChangeType::Add, NodeType::PublicDirectory),
+                ("notes.txt".into(), ChangeType::Remove, NodeType::PublicFile),
+                (
+                    "pictures".into(),
+                    ChangeType::Modify,
+                    NodeType::PublicDirectory
+                ),
+                (
+                    "pictures/cats".into(),
+                    ChangeType::Modify,
+                    NodeType::PublicDirectory
+                ),
+                (
+                    "pictures/cats/tabby.png".into(),
+                    ChangeType::Modify,
+                    NodeType::PublicFile
+                ),
+            ]
+        );
+    }
+
+    #[async_std::test]
+    async fn diff_of_same_directory_is_empty() {
+        let store = &mut MemoryBlockStore::default();
+        let PublicOpResult { root_dir, .. } = Rc::new(PublicDirectory::new(Utc::now()))
+            .write(&["cats.png".into()], Cid::default(), Utc::now(), store)
+            .await
+            .unwrap();
+
+        assert!(root_dir.diff(&root_dir, store).await.unwrap().is_empty());
+    }
+}
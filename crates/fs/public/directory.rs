@@ -13,11 +13,15 @@ use anyhow::{bail, ensure, Result};
 use async_recursion::async_recursion;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use futures::Stream;
 use libipld::Cid;
 use semver::Version;
 use serde::{ser::Error as SerError, Deserialize, Deserializer, Serialize, Serializer};
 
-use super::{PublicFile, PublicLink, PublicNode};
+use super::{
+    unixfs::{self, Chunker},
+    PublicFile, PublicLink, PublicNode,
+};
 
 //--------------------------------------------------------------------------------------------------
 // Type Definitions
@@ -408,6 +412,122 @@ impl PublicDirectory {
         }
     }
 
+    /// Reads the part of the specified file's content that falls within the given byte range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{PublicDirectory, PublicOpResult, BlockStore, MemoryBlockStore};
+    /// use libipld::IpldCodec;
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let dir = Rc::new(PublicDirectory::new(Utc::now()));
+    ///     let mut store = MemoryBlockStore::default();
+    ///     let cid = store
+    ///         .put_block(b"hello world".to_vec(), IpldCodec::Raw)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let PublicOpResult { root_dir, .. } = dir
+    ///         .write(&["text.txt".into()], cid, Utc::now(), &store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let PublicOpResult { result, .. } = root_dir
+    ///         .read_range(&["text.txt".into()], 6, 5, &mut store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(&result, b"world");
+    /// }
+    /// ```
+    pub async fn read_range<B: BlockStore>(
+        self: Rc<Self>,
+        path_segments: &[String],
+        offset: usize,
+        len: usize,
+        store: &mut B,
+    ) -> Result<PublicOpResult<Vec<u8>>> {
+        let root_dir = Rc::clone(&self);
+        let file = self.get_file(path_segments, store).await?;
+
+        Ok(PublicOpResult {
+            root_dir,
+            result: file.get_content_range(offset, len, store).await?,
+        })
+    }
+
+    /// Reads the entire content of the specified file. The content is expected to be a raw block or
+    /// a UnixFS file, like the ones written with [`write_content`](PublicDirectory::write_content).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{public::unixfs::Chunker, PublicDirectory, PublicOpResult, MemoryBlockStore};
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let dir = Rc::new(PublicDirectory::new(Utc::now()));
+    ///     let mut store = MemoryBlockStore::default();
+    ///
+    ///     let PublicOpResult { root_dir, .. } = dir
+    ///         .write_content(
+    ///             &["text.txt".into()],
+    ///             b"hello world".to_vec(),
+    ///             &Chunker::default(),
+    ///             Utc::now(),
+    ///             &mut store,
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let PublicOpResult { result, .. } = root_dir
+    ///         .read_content(&["text.txt".into()], &mut store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(&result, b"hello world");
+    /// }
+    /// ```
+    pub async fn read_content<B: BlockStore>(
+        self: Rc<Self>,
+        path_segments: &[String],
+        store: &mut B,
+    ) -> Result<PublicOpResult<Vec<u8>>> {
+        let root_dir = Rc::clone(&self);
+        let file = self.get_file(path_segments, store).await?;
+
+        Ok(PublicOpResult {
+            root_dir,
+            result: file.get_content(store).await?,
+        })
+    }
+
+    /// Follows a path and fetches the file at the end of the path.
+    async fn get_file<B: BlockStore>(
+        self: Rc<Self>,
+        path_segments: &[String],
+        store: &B,
+    ) -> Result<Rc<PublicFile>> {
+        let (path, filename) = utils::split_last(path_segments)?;
+
+        match self.get_path_nodes(path, store).await? {
+            PathNodesResult::Complete(node_path) => {
+                match node_path.tail.lookup_node(filename, store).await? {
+                    Some(PublicNode::File(file)) => Ok(file),
+                    Some(PublicNode::Dir(_)) => error(FsError::NotAFile),
+                    None => error(FsError::NotFound),
+                }
+            }
+            _ => error(FsError::NotFound),
+        }
+    }
+
     /// Writes a file to the directory.
     ///
     /// # Examples
@@ -475,6 +595,97 @@ impl PublicDirectory {
         })
     }
 
+    /// Imports the content as a UnixFS file and writes the file to the directory.
+    ///
+    /// The content is chunked with the given chunker and stored as a balanced tree of blocks, so
+    /// the file can be read from any IPFS gateway.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{public::unixfs::Chunker, PublicDirectory, PublicOpResult, MemoryBlockStore};
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let dir = Rc::new(PublicDirectory::new(Utc::now()));
+    ///     let mut store = MemoryBlockStore::default();
+    ///
+    ///     let PublicOpResult { root_dir, .. } = dir
+    ///         .write_content(
+    ///             &["pictures".into(), "cats".into(), "tabby.png".into()],
+    ///             b"not really a picture".to_vec(),
+    ///             &Chunker::rabin(),
+    ///             Utc::now(),
+    ///             &mut store,
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub async fn write_content<B: BlockStore>(
+        self: Rc<Self>,
+        path_segments: &[String],
+        content: Vec<u8>,
+        chunker: &Chunker,
+        time: DateTime<Utc>,
+        store: &mut B,
+    ) -> Result<PublicOpResult<()>> {
+        let content_cid = unixfs::import_bytes(content, chunker, store).await?;
+        self.write(path_segments, content_cid, time, store).await
+    }
+
+    /// Imports the content of the stream as a UnixFS file and writes the file to the directory.
+    ///
+    /// The stream is chunked as it is read, so the content never has to be held in memory in its
+    /// entirety.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{public::unixfs::Chunker, PublicDirectory, PublicOpResult, MemoryBlockStore};
+    /// use futures::stream;
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let dir = Rc::new(PublicDirectory::new(Utc::now()));
+    ///     let mut store = MemoryBlockStore::default();
+    ///     let chunks = stream::iter(vec![Ok(b"hello ".to_vec()), Ok(b"world".to_vec())]);
+    ///
+    ///     let PublicOpResult { root_dir, .. } = dir
+    ///         .write_content_stream(
+    ///             &["text.txt".into()],
+    ///             chunks,
+    ///             &Chunker::default(),
+    ///             Utc::now(),
+    ///             &mut store,
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let PublicOpResult { result, .. } = root_dir
+    ///         .read_content(&["text.txt".into()], &mut store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(&result, b"hello world");
+    /// }
+    /// ```
+    pub async fn write_content_stream<B: BlockStore>(
+        self: Rc<Self>,
+        path_segments: &[String],
+        content: impl Stream<Item = Result<Vec<u8>>>,
+        chunker: &Chunker,
+        time: DateTime<Utc>,
+        store: &mut B,
+    ) -> Result<PublicOpResult<()>> {
+        let content_cid = unixfs::import(content, chunker, store).await?;
+        self.write(path_segments, content_cid, time, store).await
+    }
+
     /// Creates a new directory at the specified path.
     ///
     /// # Examples
@@ -563,9 +774,13 @@ impl PublicDirectory {
         let root_dir = Rc::clone(&self);
         match self.get_path_nodes(path_segments, store).await? {
             PathNodesResult::Complete(path_nodes) => {
+                let userland = &path_nodes.tail.userland;
+                let links = userland.values().collect::<Vec<_>>();
+                let nodes = PublicLink::resolve_values(&links, store).await?;
+
                 let mut result = vec![];
-                for (name, link) in path_nodes.tail.userland.iter() {
-                    match link.resolve_value(store).await? {
+                for (name, node) in userland.keys().zip(nodes) {
+                    match node {
                         PublicNode::File(file) => {
                             result.push((name.clone(), file.metadata.clone()));
                         }
@@ -872,6 +1087,12 @@ impl AsyncSerialize for PublicDirectory {
         S: Serializer,
         B: BlockStore + ?Sized,
     {
+        // Store all children that don't have a Cid yet in one batch.
+        let links = self.userland.values().collect::<Vec<_>>();
+        PublicLink::resolve_cids(&links, store)
+            .await
+            .map_err(|e| SerError::custom(format!("{}", e)))?;
+
         let encoded_userland = {
             let mut map = BTreeMap::new();
             for (name, link) in self.userland.iter() {
@@ -933,7 +1154,7 @@ mod public_directory_tests {
     use super::*;
     use crate::{dagcbor, public::PublicFile, MemoryBlockStore};
     use chrono::Utc;
-    use libipld::Ipld;
+    use libipld::{Ipld, IpldCodec};
 
     #[async_std::test]
     async fn look_up_can_fetch_file_added_to_directory() {
@@ -1120,6 +1341,75 @@ mod public_directory_tests {
         assert_eq!(result, content_cid);
     }
 
+    #[async_std::test]
+    async fn read_range_can_fetch_slice_of_file_content() {
+        let mut store = MemoryBlockStore::default();
+        let content_cid = store
+            .put_block(b"Hello, World!".to_vec(), IpldCodec::Raw)
+            .await
+            .unwrap();
+        let time = Utc::now();
+
+        let PublicOpResult { root_dir, .. } = Rc::new(PublicDirectory::new(time))
+            .write(&["text.txt".into()], content_cid, time, &store)
+            .await
+            .unwrap();
+
+        let PublicOpResult { root_dir, result } = root_dir
+            .read_range(&["text.txt".into()], 7, 5, &mut store)
+            .await
+            .unwrap();
+
+        assert_eq!(result, b"World".to_vec());
+
+        let PublicOpResult { result, .. } = root_dir
+            .read_range(&["text.txt".into()], 7, 100, &mut store)
+            .await
+            .unwrap();
+
+        assert_eq!(result, b"World!".to_vec());
+    }
+
+    #[async_std::test]
+    async fn write_content_stores_content_as_unixfs_file() {
+        let mut store = MemoryBlockStore::default();
+        let time = Utc::now();
+        let content = (0..100_000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let chunker = Chunker::FixedSize { size: 1024 };
+
+        let PublicOpResult { root_dir, .. } = Rc::new(PublicDirectory::new(time))
+            .write_content(
+                &["videos".into(), "movie.mp4".into()],
+                content.clone(),
+                &chunker,
+                time,
+                &mut store,
+            )
+            .await
+            .unwrap();
+
+        let PublicOpResult { root_dir, result } = root_dir
+            .read(&["videos".into(), "movie.mp4".into()], &mut store)
+            .await
+            .unwrap();
+
+        assert_eq!(result.codec(), u64::from(IpldCodec::DagPb));
+
+        let PublicOpResult { root_dir, result } = root_dir
+            .read_content(&["videos".into(), "movie.mp4".into()], &mut store)
+            .await
+            .unwrap();
+
+        assert_eq!(result, content);
+
+        let PublicOpResult { result, .. } = root_dir
+            .read_range(&["videos".into(), "movie.mp4".into()], 1000, 50, &mut store)
+            .await
+            .unwrap();
+
+        assert_eq!(result, &content[1000..1050]);
+    }
+
     #[async_std::test]
     async fn path_nodes_can_generates_new_path_nodes() {
         let store = MemoryBlockStore::default();
@@ -11,6 +11,8 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::{BlockStore, Id, Metadata, NodeType};
 
+use super::unixfs;
+
 /// Represents a file in the WNFS public filesystem.
 ///
 /// # Examples
@@ -94,6 +96,64 @@ impl PublicFile {
         &self.userland
     }
 
+    /// Gets the part of the file content that falls within the given byte range. The content is
+    /// expected to be a raw block or a UnixFS file, and only the blocks covering the range are
+    /// fetched. The range is clamped to the size of the content.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{public::unixfs::{self, Chunker}, PublicFile, MemoryBlockStore};
+    /// use chrono::Utc;
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let cid = unixfs::import_bytes(b"hello world".to_vec(), &Chunker::default(), store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let file = PublicFile::new(Utc::now(), cid);
+    ///     let content = file.get_content_range(6, 5, store).await.unwrap();
+    ///
+    ///     assert_eq!(&content, b"world");
+    /// }
+    /// ```
+    pub async fn get_content_range<B: BlockStore>(
+        &self,
+        offset: usize,
+        len: usize,
+        store: &B,
+    ) -> Result<Vec<u8>> {
+        unixfs::export_range(&self.userland, offset, len, store).await
+    }
+
+    /// Gets the entire content of the file. The content is expected to be a raw block or a UnixFS
+    /// file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{public::unixfs::{self, Chunker}, PublicFile, MemoryBlockStore};
+    /// use chrono::Utc;
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let cid = unixfs::import_bytes(b"hello world".to_vec(), &Chunker::default(), store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let file = PublicFile::new(Utc::now(), cid);
+    ///     let content = file.get_content(store).await.unwrap();
+    ///
+    ///     assert_eq!(&content, b"hello world");
+    /// }
+    /// ```
+    pub async fn get_content<B: BlockStore>(&self, store: &B) -> Result<Vec<u8>> {
+        unixfs::export_bytes(&self.userland, store).await
+    }
+
     /// Stores file in provided block store.
     ///
     /// # Examples
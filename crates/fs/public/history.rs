@@ -0,0 +1,295 @@
+//! Version history of public nodes.
+
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+
+use anyhow::Result;
+use async_stream::try_stream;
+use chrono::{DateTime, Utc};
+use futures::Stream;
+use libipld::Cid;
+
+use crate::{BlockStore, Metadata};
+
+use super::PublicNode;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A past version of a public node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicNodeVersion {
+    /// The CID the version is stored under.
+    pub cid: Cid,
+    pub metadata: Metadata,
+    pub node: PublicNode,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl PublicNode {
+    /// Streams the past versions of this node by following its `previous` links.
+    ///
+    /// Versions come in topological order, so a version is only yielded after every version that
+    /// points to it. Concurrent versions are ordered by their modification time, newest first.
+    /// At most `limit` versions are yielded, and versions modified before `since` are neither
+    /// yielded nor followed.
+    ///
+    /// Versions are loaded from the store as the stream advances, so only the versions that are
+    /// yielded and those they point to are fetched. Ordering only takes the loaded versions into
+    /// account: if concurrent modification times go backwards, a version can come before another
+    /// version that points to it through a branch that hasn't been loaded yet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::rc::Rc;
+    ///
+    /// use chrono::Utc;
+    /// use futures::TryStreamExt;
+    /// use libipld::Cid;
+    /// use wnfs::{ipld::IpldCodec, BlockStore, MemoryBlockStore, PublicDirectory, PublicNode};
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let path = ["notes.txt".to_string()];
+    ///     let old_content = store.put_block(b"old".to_vec(), IpldCodec::Raw).await.unwrap();
+    ///     let new_content = store.put_block(b"new".to_vec(), IpldCodec::Raw).await.unwrap();
+    ///
+    ///     let old_dir = Rc::new(PublicDirectory::new(Utc::now()))
+    ///         .write(&path, old_content, Utc::now(), store)
+    ///         .await
+    ///         .unwrap()
+    ///         .root_dir;
+    ///     let old_cid = old_dir.store(store).await.unwrap();
+    ///
+    ///     let new_dir = Rc::clone(&old_dir)
+    ///         .write(&path, new_content, Utc::now(), store)
+    ///         .await
+    ///         .unwrap()
+    ///         .root_dir
+    ///         .base_history_on(Rc::clone(&old_dir), store)
+    ///         .await
+    ///         .unwrap()
+    ///         .root_dir;
+    ///
+    ///     // Browse the history and restore the old content of the file.
+    ///     let versions = PublicNode::Dir(Rc::clone(&new_dir))
+    ///         .history(None, None, store)
+    ///         .try_collect::<Vec<_>>()
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert_eq!(versions.len(), 1);
+    ///     assert_eq!(versions[0].cid, old_cid);
+    ///
+    ///     let old_dir = versions[0].node.as_dir().unwrap();
+    ///     let old_file = old_dir.get_node(&path, store).await.unwrap().result.unwrap();
+    ///     let content = *old_file.as_file().unwrap().get_content_cid();
+    ///
+    ///     let restored = new_dir.write(&path, content, Utc::now(), store).await.unwrap().root_dir;
+    ///     let file = restored.get_node(&path, store).await.unwrap().result.unwrap();
+    ///
+    ///     assert_eq!(file.as_file().unwrap().get_content_cid(), &old_content);
+    /// }
+    /// ```
+    pub fn history<'a, B: BlockStore>(
+        &self,
+        limit: Option<usize>,
+        since: Option<DateTime<Utc>>,
+        store: &'a B,
+    ) -> impl Stream<Item = Result<PublicNodeVersion>> + 'a {
+        let previous = self.get_previous().clone();
+        try_stream! {
+            // Versions are loaded once a yielded version points to them. For each CID, count the
+            // loaded versions that point to it and haven't been yielded yet.
+            let mut versions = BTreeMap::new();
+            let mut referrers = BTreeMap::<Cid, usize>::new();
+            let mut visited = BTreeSet::new();
+            let mut ready = BinaryHeap::new();
+            let mut to_load = previous.into_iter().collect::<Vec<_>>();
+
+            let mut count = 0;
+            while limit != Some(count) {
+                for cid in to_load.drain(..) {
+                    if !visited.insert(cid) {
+                        continue;
+                    }
+
+                    let node = store.get_deserializable::<PublicNode>(&cid).await?;
+                    let metadata = node.get_metadata().clone();
+                    if matches!((since, metadata.get_modified()), (Some(since), Some(time)) if time < since) {
+                        continue;
+                    }
+
+                    for previous_cid in node.get_previous() {
+                        *referrers.entry(*previous_cid).or_default() += 1;
+                    }
+
+                    if !referrers.contains_key(&cid) {
+                        ready.push((metadata.get_modified(), cid));
+                    }
+
+                    versions.insert(cid, PublicNodeVersion { cid, metadata, node });
+                }
+
+                // Take the newest version that no loaded version left to yield points to. Versions
+                // pointed to since they became ready are skipped and pushed again later.
+                let mut next = None;
+                while let Some((_, cid)) = ready.pop() {
+                    if !referrers.contains_key(&cid) {
+                        if let Some(version) = versions.remove(&cid) {
+                            next = Some(version);
+                            break;
+                        }
+                    }
+                }
+
+                let version = match next {
+                    Some(version) => version,
+                    None => break,
+                };
+
+                for previous_cid in version.node.get_previous() {
+                    let remaining = referrers.get_mut(previous_cid).unwrap();
+                    *remaining -= 1;
+                    if *remaining == 0 {
+                        referrers.remove(previous_cid);
+                        if let Some(previous_version) = versions.get(previous_cid) {
+                            ready.push((previous_version.metadata.get_modified(), *previous_cid));
+                        }
+                    }
+
+                    to_load.push(*previous_cid);
+                }
+
+                count += 1;
+                yield version;
+            }
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod public_history_tests {
+    use std::rc::Rc;
+
+    use chrono::{Duration, TimeZone};
+    use futures::TryStreamExt;
+
+    use super::*;
+    use crate::{MemoryBlockStore, PublicFile};
+
+    /// Stores a file version with the given previous versions, modified `minutes` after the epoch.
+    async fn put_version(
+        minutes: i64,
+        previous: &[Cid],
+        store: &mut MemoryBlockStore,
+    ) -> (Cid, PublicNode) {
+        let time = Utc.timestamp_opt(0, 0).unwrap() + Duration::minutes(minutes);
+        let mut file = PublicFile::new(time, Cid::default());
+        file.previous = previous.iter().copied().collect();
+
+        let cid = store.put_serializable(&file).await.unwrap();
+        (cid, PublicNode::File(Rc::new(file)))
+    }
+
+    async fn collect_history(
+        node: &PublicNode,
+        limit: Option<usize>,
+        since: Option<DateTime<Utc>>,
+        store: &MemoryBlockStore,
+    ) -> Vec<Cid> {
+        node.history(limit, since, store)
+            .map_ok(|version| version.cid)
+            .try_collect()
+            .await
+            .unwrap()
+    }
+
+    #[async_std::test]
+    async fn history_follows_linear_previous_links() {
+        let store = &mut MemoryBlockStore::default();
+        let (v1, _) = put_version(1, &[], store).await;
+        let (v2, _) = put_version(2, &[v1], store).await;
+        let (v3, _) = put_version(3, &[v2], store).await;
+        let (_, head) = put_version(4, &[v3], store).await;
+
+        assert_eq!(
+            collect_history(&head, None, None, store).await,
+            vec![v3, v2, v1]
+        );
+        assert_eq!(
+            collect_history(&head, Some(2), None, store).await,
+            vec![v3, v2]
+        );
+        assert_eq!(
+            collect_history(
+                &head,
+                None,
+                Some(Utc.timestamp_opt(0, 0).unwrap() + Duration::minutes(2)),
+                store
+            )
+            .await,
+            vec![v3, v2]
+        );
+    }
+
+    #[async_std::test]
+    async fn history_of_merged_versions_is_topologically_ordered() {
+        let store = &mut MemoryBlockStore::default();
+        let (base, _) = put_version(1, &[], store).await;
+        let (short, _) = put_version(5, &[base], store).await;
+        let (long_1, _) = put_version(2, &[base], store).await;
+        let (long_2, _) = put_version(3, &[long_1], store).await;
+        let (_, head) = put_version(6, &[short, long_2], store).await;
+
+        // The base is reachable through both branches, so it comes after all of their versions.
+        assert_eq!(
+            collect_history(&head, None, None, store).await,
+            vec![short, long_2, long_1, base]
+        );
+    }
+
+    #[async_std::test]
+    async fn history_only_loads_versions_up_to_the_limit() {
+        let store = &mut MemoryBlockStore::default();
+        let missing = Cid::default();
+        let (v2, _) = put_version(2, &[missing], store).await;
+        let (v3, _) = put_version(3, &[v2], store).await;
+        let (_, head) = put_version(4, &[v3], store).await;
+
+        assert_eq!(
+            collect_history(&head, Some(2), None, store).await,
+            vec![v3, v2]
+        );
+        assert!(head
+            .history(None, None, store)
+            .try_collect::<Vec<_>>()
+            .await
+            .is_err());
+    }
+
+    #[async_std::test]
+    async fn history_waits_for_loaded_versions_pointing_to_a_version() {
+        let store = &mut MemoryBlockStore::default();
+        let (base, _) = put_version(2, &[], store).await;
+        let (short, _) = put_version(5, &[base], store).await;
+        let (long_1, _) = put_version(1, &[base], store).await;
+        let (long_2, _) = put_version(3, &[long_1], store).await;
+        let (_, head) = put_version(6, &[short, long_2], store).await;
+
+        // The long branch has a clock that is behind, but the base still comes after it.
+        assert_eq!(
+            collect_history(&head, None, None, store).await,
+            vec![short, long_2, long_1, base]
+        );
+    }
+}
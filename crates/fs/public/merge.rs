@@ -0,0 +1,454 @@
+//! Three-way merge of public directories.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    rc::Rc,
+};
+
+use anyhow::Result;
+use async_recursion::async_recursion;
+use libipld::Cid;
+
+use crate::BlockStore;
+
+use super::{PublicDirectory, PublicLink, PublicNode, PublicOpResult};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// An entry that was changed differently in both directories being merged.
+///
+/// The merged directory keeps one of the two versions, but the caller is expected to look at the
+/// conflicts and resolve them, for example by writing the other version under a different name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    /// The path of the entry, relative to the merged directory.
+    pub path: Vec<String>,
+    /// The entry in the common ancestor, or `None` if it didn't exist there.
+    pub base: Option<Cid>,
+    /// Our version of the entry, or `None` if we removed it.
+    pub ours: Option<Cid>,
+    /// Their version of the entry, or `None` if they removed it.
+    pub theirs: Option<Cid>,
+}
+
+/// The state of the merge as it descends into the directories.
+struct Merger<'a, B: BlockStore> {
+    path: Vec<String>,
+    conflicts: Vec<MergeConflict>,
+    store: &'a mut B,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl PublicDirectory {
+    /// Merges two directories that were changed concurrently.
+    ///
+    /// The common ancestor of both directories is found by following their `previous` links.
+    /// Entries only changed on one side since the ancestor take that side's version and
+    /// subdirectories changed on both sides are merged recursively. Anything else changed on both
+    /// sides is reported as a conflict. In that case, an entry is preferred over its removal and
+    /// otherwise the version with the greater CID is kept, so the merged directory doesn't depend
+    /// on the order of the arguments.
+    ///
+    /// The merged directory, and every merged subdirectory, has both versions as its `previous`.
+    /// If one directory is an ancestor of the other, the descendant is returned as is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{PublicDirectory, PublicOpResult, MemoryBlockStore};
+    /// use libipld::cid::Cid;
+    /// use std::rc::Rc;
+    /// use chrono::Utc;
+    ///
+    /// #[async_std::main]
+    /// async fn main() {
+    ///     let store = &mut MemoryBlockStore::default();
+    ///     let base = Rc::new(PublicDirectory::new(Utc::now()));
+    ///
+    ///     let PublicOpResult { root_dir: ours, .. } = Rc::clone(&base)
+    ///         .write(&["cats.png".into()], Cid::default(), Utc::now(), store)
+    ///         .await
+    ///         .unwrap();
+    ///     let PublicOpResult { root_dir: ours, .. } = ours
+    ///         .base_history_on(Rc::clone(&base), store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let PublicOpResult { root_dir: theirs, .. } = Rc::clone(&base)
+    ///         .write(&["dogs.png".into()], Cid::default(), Utc::now(), store)
+    ///         .await
+    ///         .unwrap();
+    ///     let PublicOpResult { root_dir: theirs, .. } = theirs
+    ///         .base_history_on(base, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     let PublicOpResult { root_dir, result: conflicts } = ours
+    ///         .merge(theirs, store)
+    ///         .await
+    ///         .unwrap();
+    ///
+    ///     assert!(conflicts.is_empty());
+    ///     assert_eq!(root_dir.userland.len(), 2);
+    ///     assert_eq!(root_dir.previous.len(), 2);
+    /// }
+    /// ```
+    pub async fn merge<B: BlockStore>(
+        self: Rc<Self>,
+        theirs: Rc<Self>,
+        store: &mut B,
+    ) -> Result<PublicOpResult<Vec<MergeConflict>>> {
+        let ours_cid = self.store(store).await?;
+        let theirs_cid = theirs.store(store).await?;
+
+        let ours_history = Self::get_history(ours_cid, store).await?;
+        if ours_history.contains_key(&theirs_cid) {
+            return Ok(PublicOpResult {
+                root_dir: self,
+                result: vec![],
+            });
+        }
+
+        let theirs_history = Self::get_history(theirs_cid, store).await?;
+        if theirs_history.contains_key(&ours_cid) {
+            return Ok(PublicOpResult {
+                root_dir: theirs,
+                result: vec![],
+            });
+        }
+
+        // Picks the ancestor closest to both directories. The CID breaks ties so that the choice
+        // doesn't depend on the order of the arguments.
+        let base_cid = ours_history
+            .iter()
+            .filter_map(|(cid, ours_distance)| {
+                let theirs_distance = theirs_history.get(cid)?;
+                Some((
+                    *ours_distance.max(theirs_distance),
+                    ours_distance + theirs_distance,
+                    *cid,
+                ))
+            })
+            .min()
+            .map(|(_, _, cid)| cid);
+
+        let base = match base_cid {
+            Some(cid) => match store.get_deserializable::<PublicNode>(&cid).await? {
+                PublicNode::Dir(dir) => Some(dir),
+                PublicNode::File(_) => None,
+            },
+            None => None,
+        };
+
+        let mut merger = Merger {
+            path: vec![],
+            conflicts: vec![],
+            store,
+        };
+
+        let root_dir = merger
+            .merge_dirs(base.as_deref(), (&self, ours_cid), (&theirs, theirs_cid))
+            .await?;
+
+        Ok(PublicOpResult {
+            root_dir: Rc::new(root_dir),
+            result: merger.conflicts,
+        })
+    }
+
+    /// Gets the CIDs of the node and all its ancestors along with their distance from the node.
+    async fn get_history<B: BlockStore>(cid: Cid, store: &B) -> Result<BTreeMap<Cid, usize>> {
+        let mut history = BTreeMap::from([(cid, 0)]);
+        let mut queue = VecDeque::from([(cid, 0)]);
+
+        while let Some((cid, distance)) = queue.pop_front() {
+            let node = store.get_deserializable::<PublicNode>(&cid).await?;
+            for previous in node.get_previous() {
+                if !history.contains_key(previous) {
+                    history.insert(*previous, distance + 1);
+                    queue.push_back((*previous, distance + 1));
+                }
+            }
+        }
+
+        Ok(history)
+    }
+}
+
+impl<'a, B: BlockStore> Merger<'a, B> {
+    /// Merges the entries of two directories and returns a directory with both as its previous.
+    #[async_recursion(?Send)]
+    async fn merge_dirs(
+        &mut self,
+        base: Option<&'async_recursion PublicDirectory>,
+        (ours, ours_cid): (&'async_recursion PublicDirectory, Cid),
+        (theirs, theirs_cid): (&'async_recursion PublicDirectory, Cid),
+    ) -> Result<PublicDirectory> {
+        let mut dir = if ours_cid > theirs_cid {
+            ours.clone()
+        } else {
+            theirs.clone()
+        };
+
+        if let Some(time) = ours
+            .metadata
+            .get_modified()
+            .max(theirs.metadata.get_modified())
+        {
+            dir.metadata.upsert_mtime(time);
+        }
+
+        dir.previous = BTreeSet::from([ours_cid, theirs_cid]);
+        dir.userland.clear();
+
+        let names = ours
+            .userland
+            .keys()
+            .chain(theirs.userland.keys())
+            .collect::<BTreeSet<_>>();
+
+        for name in names {
+            let base_link = base.and_then(|base| base.userland.get(name));
+            let ours_link = ours.userland.get(name);
+            let theirs_link = theirs.userland.get(name);
+
+            self.path.push(name.clone());
+            let merged = self
+                .merge_entries(base_link, ours_link, theirs_link)
+                .await?;
+            self.path.pop();
+
+            if let Some(link) = merged {
+                dir.userland.insert(name.clone(), link);
+            }
+        }
+
+        Ok(dir)
+    }
+
+    /// Merges the entries with the same name in both directories. Returns `None` if the merged
+    /// directory shouldn't have the entry.
+    async fn merge_entries(
+        &mut self,
+        base: Option<&PublicLink>,
+        ours: Option<&PublicLink>,
+        theirs: Option<&PublicLink>,
+    ) -> Result<Option<PublicLink>> {
+        let base_cid = self.resolve_cid(base).await?;
+        let ours_cid = self.resolve_cid(ours).await?;
+        let theirs_cid = self.resolve_cid(theirs).await?;
+
+        if ours_cid == theirs_cid || theirs_cid == base_cid {
+            return Ok(ours.cloned());
+        }
+
+        if ours_cid == base_cid {
+            return Ok(theirs.cloned());
+        }
+
+        if let (Some(ours), Some(theirs)) = (ours, theirs) {
+            let ours_node = ours.resolve_value(self.store).await?;
+            let theirs_node = theirs.resolve_value(self.store).await?;
+
+            if let (PublicNode::Dir(ours_dir), PublicNode::Dir(theirs_dir)) =
+                (ours_node, theirs_node)
+            {
+                let base_dir = match base {
+                    Some(base) => match base.resolve_value(self.store).await? {
+                        PublicNode::Dir(dir) => Some(Rc::clone(dir)),
+                        PublicNode::File(_) => None,
+                    },
+                    None => None,
+                };
+
+                let dir = self
+                    .merge_dirs(
+                        base_dir.as_deref(),
+                        (ours_dir, ours_cid.unwrap()),
+                        (theirs_dir, theirs_cid.unwrap()),
+                    )
+                    .await?;
+
+                return Ok(Some(PublicLink::with_dir(Rc::new(dir))));
+            }
+        }
+
+        self.conflicts.push(MergeConflict {
+            path: self.path.clone(),
+            base: base_cid,
+            ours: ours_cid,
+            theirs: theirs_cid,
+        });
+
+        // Options order `None` first, so an entry is always kept over its removal.
+        Ok(if ours_cid > theirs_cid {
+            ours.cloned()
+        } else {
+            theirs.cloned()
+        })
+    }
+
+    /// Gets the CID of an optional link.
+    async fn resolve_cid(&mut self, link: Option<&PublicLink>) -> Result<Option<Cid>> {
+        Ok(match link {
+            Some(link) => Some(*link.resolve_cid(self.store).await?),
+            None => None,
+        })
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod public_merge_tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::MemoryBlockStore;
+
+    /// Writes a file to a copy of the directory and bases the copy's history on the directory.
+    async fn write_on_top(
+        base: &Rc<PublicDirectory>,
+        path: &[&str],
+        content_cid: Cid,
+        store: &mut MemoryBlockStore,
+    ) -> Rc<PublicDirectory> {
+        let path = path.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let PublicOpResult { root_dir, .. } = Rc::clone(base)
+            .write(&path, content_cid, Utc::now(), store)
+            .await
+            .unwrap();
+
+        root_dir
+            .base_history_on(Rc::clone(base), store)
+            .await
+            .unwrap()
+            .root_dir
+    }
+
+    async fn content_cid(content: &[u8], store: &mut MemoryBlockStore) -> Cid {
+        store
+            .put_block(content.to_vec(), libipld::IpldCodec::Raw)
+            .await
+            .unwrap()
+    }
+
+    #[async_std::test]
+    async fn non_conflicting_changes_in_nested_directories_are_merged() {
+        let store = &mut MemoryBlockStore::default();
+        let cid = content_cid(b"cat", store).await;
+        let root = Rc::new(PublicDirectory::new(Utc::now()));
+        let base = write_on_top(&root, &["pictures", "cats", "tabby.png"], cid, store).await;
+
+        let ours = write_on_top(&base, &["pictures", "cats", "luna.png"], cid, store).await;
+        let theirs = write_on_top(&base, &["pictures", "cats", "milo.png"], cid, store).await;
+
+        let ours_cid = ours.store(store).await.unwrap();
+        let theirs_cid = theirs.store(store).await.unwrap();
+
+        let PublicOpResult { root_dir, result } = ours.merge(theirs, store).await.unwrap();
+
+        let PublicOpResult {
+            result: entries, ..
+        } = Rc::clone(&root_dir)
+            .ls(&["pictures".into(), "cats".into()], store)
+            .await
+            .unwrap();
+
+        let names = entries
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+
+        assert!(result.is_empty());
+        assert_eq!(names, vec!["luna.png", "milo.png", "tabby.png"]);
+        assert_eq!(root_dir.previous, BTreeSet::from([ours_cid, theirs_cid]));
+    }
+
+    #[async_std::test]
+    async fn conflicting_changes_are_reported_and_merge_is_deterministic() {
+        let store = &mut MemoryBlockStore::default();
+        let root = Rc::new(PublicDirectory::new(Utc::now()));
+        let base_content = content_cid(b"base", store).await;
+        let ours_content = content_cid(b"ours", store).await;
+        let theirs_content = content_cid(b"theirs", store).await;
+
+        let base = write_on_top(&root, &["notes.txt"], base_content, store).await;
+        let ours = write_on_top(&base, &["notes.txt"], ours_content, store).await;
+        let theirs = write_on_top(&base, &["notes.txt"], theirs_content, store).await;
+
+        let PublicOpResult {
+            root_dir: merged,
+            result: conflicts,
+        } = Rc::clone(&ours)
+            .merge(Rc::clone(&theirs), store)
+            .await
+            .unwrap();
+
+        let PublicOpResult {
+            root_dir: reverse_merged,
+            ..
+        } = theirs.merge(ours, store).await.unwrap();
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].path, vec!["notes.txt".to_string()]);
+        assert!(conflicts[0].base.is_some());
+        assert_ne!(conflicts[0].ours, conflicts[0].theirs);
+        assert_eq!(
+            merged.store(store).await.unwrap(),
+            reverse_merged.store(store).await.unwrap()
+        );
+    }
+
+    #[async_std::test]
+    async fn removal_on_one_side_is_kept() {
+        let store = &mut MemoryBlockStore::default();
+        let cid = content_cid(b"cat", store).await;
+        let root = Rc::new(PublicDirectory::new(Utc::now()));
+        let base = write_on_top(&root, &["cats.png"], cid, store).await;
+
+        let PublicOpResult { root_dir: ours, .. } = Rc::clone(&base)
+            .rm(&["cats.png".into()], store)
+            .await
+            .unwrap();
+        let ours = ours
+            .base_history_on(Rc::clone(&base), store)
+            .await
+            .unwrap()
+            .root_dir;
+
+        let theirs = write_on_top(&base, &["dogs.png"], cid, store).await;
+
+        let PublicOpResult { root_dir, result } = ours.merge(theirs, store).await.unwrap();
+
+        assert!(result.is_empty());
+        assert_eq!(
+            root_dir.userland.keys().collect::<Vec<_>>(),
+            vec!["dogs.png"]
+        );
+    }
+
+    #[async_std::test]
+    async fn merging_with_an_ancestor_returns_the_descendant() {
+        let store = &mut MemoryBlockStore::default();
+        let cid = content_cid(b"cat", store).await;
+        let root = Rc::new(PublicDirectory::new(Utc::now()));
+        let base = write_on_top(&root, &["cats.png"], cid, store).await;
+        let descendant = write_on_top(&base, &["dogs.png"], cid, store).await;
+
+        let PublicOpResult { root_dir, result } = Rc::clone(&base)
+            .merge(Rc::clone(&descendant), store)
+            .await
+            .unwrap();
+
+        assert!(result.is_empty());
+        assert!(Rc::ptr_eq(&root_dir, &descendant));
+    }
+}
@@ -1,9 +1,15 @@
+mod diff;
 mod directory;
 mod file;
+mod history;
 mod link;
+mod merge;
 mod node;
+pub mod unixfs;
 
 pub use directory::*;
 pub use file::*;
+pub use history::*;
 use link::*;
+pub use merge::*;
 pub use node::*;
@@ -9,7 +9,7 @@ use libipld::{Cid, Ipld};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 use super::{PublicDirectory, PublicFile};
-use crate::{common::BlockStore, AsyncSerialize, FsError, Id, NodeType};
+use crate::{common::BlockStore, AsyncSerialize, FsError, Id, Metadata, NodeType};
 
 //--------------------------------------------------------------------------------------------------
 // Type Definitions
@@ -144,6 +144,27 @@ impl PublicNode {
         }
     }
 
+    /// Gets the metadata of a node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::{PublicDirectory, PublicNode, Metadata};
+    /// use chrono::Utc;
+    /// use std::rc::Rc;
+    ///
+    /// let time = Utc::now();
+    /// let node = PublicNode::Dir(Rc::new(PublicDirectory::new(time)));
+    ///
+    /// assert_eq!(node.get_metadata(), &Metadata::new(time));
+    /// ```
+    pub fn get_metadata(&self) -> &Metadata {
+        match self {
+            Self::File(file) => file.get_metadata(),
+            Self::Dir(dir) => dir.get_metadata(),
+        }
+    }
+
     /// Casts a node to a directory.
     ///
     /// # Examples
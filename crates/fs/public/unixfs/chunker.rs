@@ -0,0 +1,268 @@
+use anyhow::{ensure, Result};
+
+use crate::FsError;
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The default chunk size. This is the same size IPFS uses by default.
+pub const DEFAULT_CHUNK_SIZE: usize = 256 * 1024;
+
+/// The number of bytes the rolling hash of the rabin chunker is computed over.
+const RABIN_WINDOW_SIZE: usize = 48;
+
+/// The base of the polynomial used by the rolling hash of the rabin chunker.
+const RABIN_PRIME: u64 = 0x3DA3358B4DC173;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// Determines how file content is split into chunks before it is stored.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs::public::unixfs::Chunker;
+///
+/// let chunker = Chunker::FixedSize { size: 1024 };
+/// let chunks = chunker.chunk(&[0; 2500]).unwrap();
+///
+/// assert_eq!(chunks.iter().map(|c| c.len()).collect::<Vec<_>>(), vec![1024, 1024, 452]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Chunker {
+    /// Splits content into chunks of the same size.
+    FixedSize { size: usize },
+    /// Splits content at boundaries determined by a Rabin-Karp rolling hash of the content.
+    ///
+    /// Because the boundaries depend on the content rather than on offsets, an edit only changes
+    /// the chunks around it and the rest of the chunks can be deduplicated.
+    Rabin {
+        min_size: usize,
+        avg_size: usize,
+        max_size: usize,
+    },
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl Chunker {
+    /// Creates a rabin chunker with the same default sizes IPFS uses.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::public::unixfs::{Chunker, DEFAULT_CHUNK_SIZE};
+    ///
+    /// let chunker = Chunker::rabin();
+    ///
+    /// assert_eq!(
+    ///     chunker,
+    ///     Chunker::Rabin {
+    ///         min_size: DEFAULT_CHUNK_SIZE / 3,
+    ///         avg_size: DEFAULT_CHUNK_SIZE,
+    ///         max_size: DEFAULT_CHUNK_SIZE + DEFAULT_CHUNK_SIZE / 2,
+    ///     }
+    /// );
+    /// ```
+    pub fn rabin() -> Self {
+        Self::Rabin {
+            min_size: DEFAULT_CHUNK_SIZE / 3,
+            avg_size: DEFAULT_CHUNK_SIZE,
+            max_size: DEFAULT_CHUNK_SIZE + DEFAULT_CHUNK_SIZE / 2,
+        }
+    }
+
+    /// Splits the content into chunks.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use wnfs::public::unixfs::Chunker;
+    ///
+    /// let chunker = Chunker::Rabin { min_size: 16, avg_size: 64, max_size: 128 };
+    /// let content = (0..1000).map(|i| (i * 7 % 251) as u8).collect::<Vec<_>>();
+    /// let chunks = chunker.chunk(&content).unwrap();
+    ///
+    /// assert_eq!(chunks.concat(), content);
+    /// assert!(chunks.iter().all(|c| c.len() <= 128));
+    /// ```
+    pub fn chunk<'a>(&self, content: &'a [u8]) -> Result<Vec<&'a [u8]>> {
+        self.validate()?;
+
+        let mut chunks = Vec::new();
+        let mut remaining = content;
+        while !remaining.is_empty() {
+            let window = &remaining[..remaining.len().min(self.max_chunk_size())];
+            let (chunk, rest) = remaining.split_at(self.find_boundary(window));
+            chunks.push(chunk);
+            remaining = rest;
+        }
+
+        Ok(chunks)
+    }
+
+    /// Checks that the chunker parameters can produce chunks.
+    pub(crate) fn validate(&self) -> Result<()> {
+        match self {
+            Self::FixedSize { size } => ensure!(*size > 0, FsError::InvalidChunker),
+            Self::Rabin {
+                min_size,
+                avg_size,
+                max_size,
+            } => ensure!(
+                0 < *min_size && min_size < avg_size && avg_size <= max_size,
+                FsError::InvalidChunker
+            ),
+        }
+
+        Ok(())
+    }
+
+    /// The largest chunk the chunker produces.
+    pub(crate) fn max_chunk_size(&self) -> usize {
+        match self {
+            Self::FixedSize { size } => *size,
+            Self::Rabin { max_size, .. } => *max_size,
+        }
+    }
+
+    /// Finds the end of the first chunk in the window. The window should be no larger than the
+    /// maximum chunk size. If there is no boundary, the whole window is the chunk.
+    pub(crate) fn find_boundary(&self, window: &[u8]) -> usize {
+        match self {
+            Self::FixedSize { size } => window.len().min(*size),
+            Self::Rabin {
+                min_size, avg_size, ..
+            } => Self::find_rabin_boundary(window, *min_size, *avg_size),
+        }
+    }
+
+    /// Finds the first position after the minimum size where the top bits of the rolling hash are
+    /// all zero. The number of bits is chosen so that, on average, chunks end up close to the
+    /// average size.
+    fn find_rabin_boundary(window: &[u8], min_size: usize, avg_size: usize) -> usize {
+        if window.len() <= min_size {
+            return window.len();
+        }
+
+        let bits = usize::BITS - (avg_size - min_size).leading_zeros() - 1;
+        let out_factor = RABIN_PRIME.wrapping_pow(RABIN_WINDOW_SIZE as u32);
+
+        let mut hash = 0u64;
+        for (index, byte) in window.iter().enumerate() {
+            hash = hash
+                .wrapping_mul(RABIN_PRIME)
+                .wrapping_add(u64::from(*byte) + 1);
+
+            if index >= RABIN_WINDOW_SIZE {
+                let removed = u64::from(window[index - RABIN_WINDOW_SIZE]) + 1;
+                hash = hash.wrapping_sub(out_factor.wrapping_mul(removed));
+            }
+
+            // With no bits to check, every position after the minimum size is a boundary.
+            if index >= min_size && hash.checked_shr(u64::BITS - bits).unwrap_or(0) == 0 {
+                return index + 1;
+            }
+        }
+
+        window.len()
+    }
+}
+
+impl Default for Chunker {
+    fn default() -> Self {
+        Self::FixedSize {
+            size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod chunker_tests {
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use rand_core::RngCore;
+
+    use super::*;
+
+    #[test]
+    fn rabin_chunks_are_within_bounds() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let mut content = vec![0u8; 200_000];
+        rng.fill_bytes(&mut content);
+
+        let chunker = Chunker::Rabin {
+            min_size: 256,
+            avg_size: 1024,
+            max_size: 4096,
+        };
+
+        let chunks = chunker.chunk(&content).unwrap();
+        let (last, rest) = chunks.split_last().unwrap();
+
+        assert_eq!(chunks.concat(), content);
+        assert!(!last.is_empty() && last.len() <= 4096);
+        assert!(rest.iter().all(|c| (257..=4096).contains(&c.len())));
+        assert!(rest.iter().any(|c| c.len() < 4096));
+    }
+
+    #[test]
+    fn rabin_chunks_are_preserved_after_insertion() {
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let mut content = vec![0u8; 200_000];
+        rng.fill_bytes(&mut content);
+
+        let chunker = Chunker::Rabin {
+            min_size: 256,
+            avg_size: 1024,
+            max_size: 4096,
+        };
+
+        let edited = [b"inserted".as_slice(), &content].concat();
+
+        let original_chunks = chunker.chunk(&content).unwrap();
+        let edited_chunks = chunker.chunk(&edited).unwrap();
+
+        let shared = edited_chunks
+            .iter()
+            .filter(|c| original_chunks.contains(c))
+            .count();
+
+        assert!(shared + 2 >= original_chunks.len());
+    }
+
+    #[test]
+    fn invalid_chunkers_are_rejected() {
+        assert!(Chunker::FixedSize { size: 0 }.chunk(b"hello").is_err());
+        assert!(Chunker::Rabin {
+            min_size: 10,
+            avg_size: 5,
+            max_size: 20
+        }
+        .chunk(b"hello")
+        .is_err());
+    }
+
+    #[test]
+    fn rabin_chunks_with_average_just_above_minimum_end_after_minimum() {
+        let content = (0..100).map(|i| (i * 7 % 251) as u8).collect::<Vec<_>>();
+        let chunker = Chunker::Rabin {
+            min_size: 4,
+            avg_size: 5,
+            max_size: 16,
+        };
+
+        let chunks = chunker.chunk(&content).unwrap();
+
+        assert_eq!(chunks.concat(), content);
+        assert!(chunks.iter().all(|c| c.len() == 5));
+    }
+}
@@ -0,0 +1,352 @@
+use anyhow::{bail, ensure, Result};
+use async_stream::try_stream;
+use futures::{Stream, TryStreamExt};
+use libipld::{pb::PbNode, Cid, IpldCodec};
+
+use crate::{utils, BlockStore, FsError};
+
+use super::protobuf::{DataType, UnixFsData};
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A node of a UnixFS file.
+struct FileNode {
+    /// The content stored in the node itself.
+    data: Vec<u8>,
+    /// The children of the node along with the size of their content.
+    children: Vec<(Cid, usize)>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl FileNode {
+    /// Decodes a raw or DAG-PB block into a file node.
+    fn decode(cid: &Cid, bytes: &[u8]) -> Result<Self> {
+        match IpldCodec::try_from(cid.codec()) {
+            Ok(IpldCodec::Raw) => Ok(Self {
+                data: bytes.to_vec(),
+                children: vec![],
+            }),
+            Ok(IpldCodec::DagPb) => Self::decode_dag_pb(bytes),
+            _ => bail!(FsError::UnsupportedCodec(cid.codec())),
+        }
+    }
+
+    /// Decodes a DAG-PB node holding UnixFS file data.
+    fn decode_dag_pb(bytes: &[u8]) -> Result<Self> {
+        let node =
+            PbNode::from_bytes(bytes).map_err(|e| FsError::InvalidUnixFsData(e.to_string()))?;
+        ensure!(
+            !node.data.is_empty(),
+            FsError::InvalidUnixFsData("Missing data".into())
+        );
+        let data = UnixFsData::decode(&node.data)?;
+
+        match data.r#type {
+            DataType::File | DataType::Raw => (),
+            DataType::Directory | DataType::HamtShard => bail!(FsError::NotAFile),
+            other => bail!(FsError::InvalidUnixFsData(format!(
+                "Unsupported node type {other:?}"
+            ))),
+        }
+
+        ensure!(
+            data.blocksizes.len() == node.links.len(),
+            FsError::InvalidUnixFsData("Block sizes don't match links".into())
+        );
+
+        let children = node
+            .links
+            .into_iter()
+            .zip(data.blocksizes)
+            .map(|(link, size)| Ok((link.cid, usize::try_from(size)?)))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            data: data.data.unwrap_or_default(),
+            children,
+        })
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Streams the content of the UnixFS file with the given root CID, one leaf at a time.
+///
+/// Both raw leaves and DAG-PB leaves with inline data are supported, so files imported by other
+/// IPFS implementations can be exported as well.
+///
+/// # Examples
+///
+/// ```
+/// use futures::TryStreamExt;
+/// use wnfs::{public::unixfs::{self, Chunker}, MemoryBlockStore};
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &mut MemoryBlockStore::default();
+///     let chunker = Chunker::FixedSize { size: 4 };
+///
+///     let cid = unixfs::import_bytes(b"hello world".to_vec(), &chunker, store)
+///         .await
+///         .unwrap();
+///
+///     let chunks = unixfs::export(&cid, store).try_collect::<Vec<_>>().await.unwrap();
+///
+///     assert_eq!(chunks, vec![b"hell".to_vec(), b"o wo".to_vec(), b"rld".to_vec()]);
+/// }
+/// ```
+pub fn export<'a, B: BlockStore>(
+    cid: &Cid,
+    store: &'a B,
+) -> impl Stream<Item = Result<Vec<u8>>> + 'a {
+    export_range_stream(*cid, 0, usize::MAX, store)
+}
+
+/// Gets the entire content of the UnixFS file with the given root CID.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs::{public::unixfs::{self, Chunker}, MemoryBlockStore};
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &mut MemoryBlockStore::default();
+///
+///     let cid = unixfs::import_bytes(b"hello world".to_vec(), &Chunker::default(), store)
+///         .await
+///         .unwrap();
+///
+///     let bytes = unixfs::export_bytes(&cid, store).await.unwrap();
+///
+///     assert_eq!(&bytes, b"hello world");
+/// }
+/// ```
+pub async fn export_bytes<B: BlockStore>(cid: &Cid, store: &B) -> Result<Vec<u8>> {
+    export(cid, store).try_concat().await
+}
+
+/// Gets the part of the UnixFS file's content that falls within the given byte range. Subtrees
+/// outside of the range are skipped without being fetched. The range is clamped to the size of
+/// the file.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs::{public::unixfs::{self, Chunker}, MemoryBlockStore};
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &mut MemoryBlockStore::default();
+///     let chunker = Chunker::FixedSize { size: 4 };
+///
+///     let cid = unixfs::import_bytes(b"hello world".to_vec(), &chunker, store)
+///         .await
+///         .unwrap();
+///
+///     let bytes = unixfs::export_range(&cid, 6, 5, store).await.unwrap();
+///
+///     assert_eq!(&bytes, b"world");
+/// }
+/// ```
+pub async fn export_range<B: BlockStore>(
+    cid: &Cid,
+    offset: usize,
+    len: usize,
+    store: &B,
+) -> Result<Vec<u8>> {
+    export_range_stream(*cid, offset, len, store)
+        .try_concat()
+        .await
+}
+
+/// Streams the parts of the leaves that fall within the given byte range.
+fn export_range_stream<B: BlockStore>(
+    cid: Cid,
+    offset: usize,
+    len: usize,
+    store: &B,
+) -> impl Stream<Item = Result<Vec<u8>>> + '_ {
+    try_stream! {
+        let end = offset.saturating_add(len);
+
+        // Depth-first traversal. Each entry holds a CID and the offset of its content in the file.
+        let mut stack = vec![(cid, 0usize)];
+        while let Some((cid, node_offset)) = stack.pop() {
+            let FileNode { data, children } = FileNode::decode(&cid, &store.get_block(&cid).await?)?;
+
+            if !data.is_empty() {
+                let slice = get_intersection(&data, node_offset, offset, end);
+                if !slice.is_empty() {
+                    yield slice.to_vec();
+                }
+            }
+
+            // Block sizes come from the file itself, so they are checked for overflow.
+            let mut child_offset = checked_offset(node_offset, data.len())?;
+            let mut children_in_range = vec![];
+            for (child, size) in children {
+                let child_end = checked_offset(child_offset, size)?;
+                if child_offset < end && offset < child_end {
+                    children_in_range.push((child, child_offset));
+                }
+
+                child_offset = child_end;
+            }
+
+            stack.extend(children_in_range.into_iter().rev());
+        }
+    }
+}
+
+/// Adds a size to an offset in the file, failing if the file claims to be larger than can be
+/// addressed.
+fn checked_offset(offset: usize, size: usize) -> Result<usize> {
+    offset
+        .checked_add(size)
+        .ok_or_else(|| FsError::InvalidUnixFsData("File size overflows".into()).into())
+}
+
+/// Gets the part of the data, which starts at the given offset in the file, that falls within
+/// the range.
+fn get_intersection(data: &[u8], data_offset: usize, start: usize, end: usize) -> &[u8] {
+    let relative_start = start.saturating_sub(data_offset);
+    let relative_end = end.saturating_sub(data_offset);
+    utils::get_range(
+        data,
+        relative_start,
+        relative_end.saturating_sub(relative_start),
+    )
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod exporter_tests {
+    use libipld::{pb::PbLink, IpldCodec};
+    use proptest::test_runner::{RngAlgorithm, TestRng};
+    use rand_core::RngCore;
+
+    use super::*;
+    use crate::{
+        public::unixfs::{import_bytes, protobuf::encode_dag_pb, Chunker},
+        MemoryBlockStore,
+    };
+
+    #[async_std::test]
+    async fn ranges_of_multi_level_trees_can_be_exported() {
+        let store = &mut MemoryBlockStore::default();
+        let rng = &mut TestRng::deterministic_rng(RngAlgorithm::ChaCha);
+        let mut content = vec![0u8; 10_000];
+        rng.fill_bytes(&mut content);
+
+        let chunker = Chunker::FixedSize { size: 16 };
+        let cid = import_bytes(content.clone(), &chunker, store)
+            .await
+            .unwrap();
+
+        for (offset, len) in [(0, 10), (15, 2), (2780, 3000), (9990, 100), (20_000, 5)] {
+            let bytes = export_range(&cid, offset, len, store).await.unwrap();
+            assert_eq!(bytes, utils::get_range(&content, offset, len));
+        }
+
+        assert_eq!(export_bytes(&cid, store).await.unwrap(), content);
+    }
+
+    #[async_std::test]
+    async fn files_with_inline_data_leaves_can_be_exported() {
+        let store = &mut MemoryBlockStore::default();
+
+        let mut links = vec![];
+        for chunk in [b"hello ".as_slice(), b"world"] {
+            let mut data = UnixFsData::file(chunk.len() as u64, vec![]);
+            data.data = Some(chunk.to_vec());
+
+            let leaf = PbNode {
+                links: vec![],
+                data: data.encode().into_boxed_slice(),
+            };
+            let leaf = encode_dag_pb(&leaf);
+
+            let tsize = leaf.len() as u64;
+            links.push(PbLink {
+                cid: store.put_block(leaf, IpldCodec::DagPb).await.unwrap(),
+                name: String::new(),
+                size: tsize,
+            });
+        }
+
+        let root = PbNode {
+            links,
+            data: UnixFsData::file(11, vec![6, 5]).encode().into_boxed_slice(),
+        };
+        let cid = store
+            .put_block(encode_dag_pb(&root), IpldCodec::DagPb)
+            .await
+            .unwrap();
+
+        assert_eq!(export_bytes(&cid, store).await.unwrap(), b"hello world");
+        assert_eq!(export_range(&cid, 4, 4, store).await.unwrap(), b"o wo");
+    }
+
+    #[async_std::test]
+    async fn directories_cannot_be_exported() {
+        let store = &mut MemoryBlockStore::default();
+        let mut data = UnixFsData::file(0, vec![]);
+        data.r#type = DataType::Directory;
+
+        let node = PbNode {
+            links: vec![],
+            data: data.encode().into_boxed_slice(),
+        };
+        let cid = store
+            .put_block(encode_dag_pb(&node), IpldCodec::DagPb)
+            .await
+            .unwrap();
+
+        assert!(export_bytes(&cid, store).await.is_err());
+    }
+
+    #[async_std::test]
+    async fn files_with_overflowing_block_sizes_are_rejected() {
+        let store = &mut MemoryBlockStore::default();
+        let leaf = store
+            .put_block(b"hello".to_vec(), IpldCodec::Raw)
+            .await
+            .unwrap();
+
+        let links = (0..2)
+            .map(|_| PbLink {
+                cid: leaf,
+                name: String::new(),
+                size: 5,
+            })
+            .collect();
+        let root = PbNode {
+            links,
+            data: UnixFsData::file(u64::MAX, vec![u64::MAX, u64::MAX])
+                .encode()
+                .into_boxed_slice(),
+        };
+        let cid = store
+            .put_block(encode_dag_pb(&root), IpldCodec::DagPb)
+            .await
+            .unwrap();
+
+        let error = export_bytes(&cid, store).await.unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<FsError>(),
+            Some(FsError::InvalidUnixFsData(_))
+        ));
+    }
+}
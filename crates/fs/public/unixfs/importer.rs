@@ -0,0 +1,347 @@
+use anyhow::Result;
+use futures::{future, pin_mut, stream, Stream, StreamExt};
+use libipld::{
+    pb::{PbLink, PbNode},
+    Cid, IpldCodec,
+};
+
+use crate::BlockStore;
+
+use super::{
+    protobuf::{self, UnixFsData},
+    Chunker,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The maximum number of links in a node of the tree. This is the same number IPFS uses for its
+/// balanced layout.
+pub const MAX_LINKS_PER_NODE: usize = 174;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// A link to a subtree that has already been stored.
+#[derive(Debug, Clone)]
+struct UnixFsLink {
+    cid: Cid,
+    /// The number of content bytes in the subtree.
+    filesize: u64,
+    /// The total number of bytes of all blocks in the subtree.
+    tsize: u64,
+}
+
+/// Builds a balanced tree bottom-up as leaves are added.
+///
+/// Each level holds the links that don't have a parent yet. As soon as a level is full, its links
+/// are stored as a node whose link is added to the level above.
+#[derive(Debug, Default)]
+struct TreeBuilder {
+    levels: Vec<Vec<UnixFsLink>>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl TreeBuilder {
+    /// Stores a chunk as a raw leaf and adds it to the tree.
+    async fn add_leaf<B: BlockStore>(&mut self, chunk: Vec<u8>, store: &mut B) -> Result<()> {
+        let size = chunk.len() as u64;
+        let cid = store.put_block(chunk, IpldCodec::Raw).await?;
+
+        self.add_link(
+            0,
+            UnixFsLink {
+                cid,
+                filesize: size,
+                tsize: size,
+            },
+            store,
+        )
+        .await
+    }
+
+    /// Adds a link to a level, storing the level as a node if it becomes full.
+    async fn add_link<B: BlockStore>(
+        &mut self,
+        mut level: usize,
+        mut link: UnixFsLink,
+        store: &mut B,
+    ) -> Result<()> {
+        loop {
+            if self.levels.len() <= level {
+                self.levels.push(vec![]);
+            }
+
+            self.levels[level].push(link);
+            if self.levels[level].len() < MAX_LINKS_PER_NODE {
+                return Ok(());
+            }
+
+            link = self.store_level(level, store).await?;
+            level += 1;
+        }
+    }
+
+    /// Stores the remaining links of every level and returns the root of the tree.
+    async fn finish<B: BlockStore>(mut self, store: &mut B) -> Result<Cid> {
+        if self.levels.is_empty() {
+            self.add_leaf(vec![], store).await?;
+        }
+
+        let mut level = 0;
+        loop {
+            let is_top = self.levels[level + 1..].iter().all(Vec::is_empty);
+            if is_top && self.levels[level].len() == 1 {
+                return Ok(self.levels[level][0].cid);
+            }
+
+            if !self.levels[level].is_empty() {
+                let link = self.store_level(level, store).await?;
+                if self.levels.len() <= level + 1 {
+                    self.levels.push(vec![]);
+                }
+
+                self.levels[level + 1].push(link);
+            }
+
+            level += 1;
+        }
+    }
+
+    /// Stores the links of a level as a UnixFS file node and clears the level.
+    async fn store_level<B: BlockStore>(
+        &mut self,
+        level: usize,
+        store: &mut B,
+    ) -> Result<UnixFsLink> {
+        let links = std::mem::take(&mut self.levels[level]);
+
+        let filesize = links.iter().map(|link| link.filesize).sum();
+        let blocksizes = links.iter().map(|link| link.filesize).collect();
+        let children_tsize = links.iter().map(|link| link.tsize).sum::<u64>();
+
+        let node = PbNode {
+            links: links
+                .into_iter()
+                .map(|link| PbLink {
+                    cid: link.cid,
+                    name: String::new(),
+                    size: link.tsize,
+                })
+                .collect(),
+            data: UnixFsData::file(filesize, blocksizes)
+                .encode()
+                .into_boxed_slice(),
+        };
+
+        let bytes = protobuf::encode_dag_pb(&node);
+        let tsize = bytes.len() as u64 + children_tsize;
+        let cid = store.put_block(bytes, IpldCodec::DagPb).await?;
+
+        Ok(UnixFsLink {
+            cid,
+            filesize,
+            tsize,
+        })
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Chunks the content of the stream and stores it as a UnixFS file, returning the CID of its root.
+///
+/// Chunks are stored as raw leaves and linked together by a balanced tree of DAG-PB nodes, which
+/// is the layout `ipfs add --cid-version=1` produces. Content that fits in one chunk is stored as a
+/// single raw block.
+///
+/// # Examples
+///
+/// ```
+/// use futures::stream;
+/// use wnfs::{public::unixfs::{self, Chunker}, MemoryBlockStore};
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &mut MemoryBlockStore::default();
+///     let content = stream::iter(vec![Ok(b"hello ".to_vec()), Ok(b"world".to_vec())]);
+///
+///     let cid = unixfs::import(content, &Chunker::default(), store).await.unwrap();
+///     let bytes = unixfs::export_bytes(&cid, store).await.unwrap();
+///
+///     assert_eq!(&bytes, b"hello world");
+/// }
+/// ```
+pub async fn import<B: BlockStore>(
+    content: impl Stream<Item = Result<Vec<u8>>>,
+    chunker: &Chunker,
+    store: &mut B,
+) -> Result<Cid> {
+    chunker.validate()?;
+
+    let max_chunk_size = chunker.max_chunk_size();
+    let mut builder = TreeBuilder::default();
+    let mut buffer = Vec::new();
+
+    pin_mut!(content);
+    while let Some(bytes) = content.next().await {
+        buffer.extend_from_slice(&bytes?);
+
+        // Only look for a boundary once a full window is available, so that boundaries don't
+        // depend on how the content was split into stream items.
+        let mut position = 0;
+        while buffer.len() - position >= max_chunk_size {
+            let window = &buffer[position..position + max_chunk_size];
+            let boundary = chunker.find_boundary(window);
+            builder.add_leaf(window[..boundary].to_vec(), store).await?;
+            position += boundary;
+        }
+
+        buffer.drain(..position);
+    }
+
+    for chunk in chunker.chunk(&buffer)? {
+        builder.add_leaf(chunk.to_vec(), store).await?;
+    }
+
+    builder.finish(store).await
+}
+
+/// Chunks the content and stores it as a UnixFS file, returning the CID of its root.
+///
+/// # Examples
+///
+/// ```
+/// use wnfs::{public::unixfs::{self, Chunker}, MemoryBlockStore};
+///
+/// #[async_std::main]
+/// async fn main() {
+///     let store = &mut MemoryBlockStore::default();
+///
+///     let cid = unixfs::import_bytes(b"hello world".to_vec(), &Chunker::rabin(), store)
+///         .await
+///         .unwrap();
+///     let bytes = unixfs::export_bytes(&cid, store).await.unwrap();
+///
+///     assert_eq!(&bytes, b"hello world");
+/// }
+/// ```
+pub async fn import_bytes<B: BlockStore>(
+    content: Vec<u8>,
+    chunker: &Chunker,
+    store: &mut B,
+) -> Result<Cid> {
+    import(stream::once(future::ok(content)), chunker, store).await
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod importer_tests {
+    use libipld::multibase::Base;
+
+    use super::*;
+    use crate::{public::unixfs, MemoryBlockStore};
+
+    #[async_std::test]
+    async fn small_content_is_stored_as_a_single_raw_block() {
+        let store = &mut MemoryBlockStore::default();
+
+        let cid = import_bytes(b"hello world".to_vec(), &Chunker::default(), store)
+            .await
+            .unwrap();
+
+        // Same CID as `ipfs add --cid-version=1` produces for the same content.
+        assert_eq!(
+            cid.to_string_of_base(Base::Base32Lower).unwrap(),
+            "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e"
+        );
+    }
+
+    #[async_std::test]
+    async fn multi_block_content_has_the_same_cid_as_in_ipfs() {
+        let store = &mut MemoryBlockStore::default();
+
+        let cid = import_bytes(vec![0; 600_000], &Chunker::default(), store)
+            .await
+            .unwrap();
+
+        // The CID of `head -c 600000 /dev/zero | ipfs add --cid-version=1 --raw-leaves -Q`.
+        assert_eq!(
+            cid.to_string_of_base(Base::Base32Lower).unwrap(),
+            "bafybeib7pcuusxw7atqqc4apxvkezssdjbqkkhymjpo5x7bsj5sytn2ph4"
+        );
+    }
+
+    #[async_std::test]
+    async fn empty_content_can_be_imported() {
+        let store = &mut MemoryBlockStore::default();
+
+        let cid = import_bytes(vec![], &Chunker::default(), store)
+            .await
+            .unwrap();
+
+        assert_eq!(cid.codec(), u64::from(IpldCodec::Raw));
+        assert!(unixfs::export_bytes(&cid, store).await.unwrap().is_empty());
+    }
+
+    #[async_std::test]
+    async fn tree_is_balanced() {
+        let store = &mut MemoryBlockStore::default();
+        let chunker = Chunker::FixedSize { size: 1 };
+        let content = vec![1u8; MAX_LINKS_PER_NODE + 1];
+
+        let cid = import_bytes(content.clone(), &chunker, store)
+            .await
+            .unwrap();
+
+        let root = PbNode::from_bytes(&store.get_block(&cid).await.unwrap()).unwrap();
+        let first =
+            PbNode::from_bytes(&store.get_block(&root.links[0].cid).await.unwrap()).unwrap();
+        let second =
+            PbNode::from_bytes(&store.get_block(&root.links[1].cid).await.unwrap()).unwrap();
+
+        assert_eq!(root.links.len(), 2);
+        assert_eq!(first.links.len(), MAX_LINKS_PER_NODE);
+        assert_eq!(second.links.len(), 1);
+        assert_eq!(unixfs::export_bytes(&cid, store).await.unwrap(), content);
+    }
+
+    #[async_std::test]
+    async fn stream_item_sizes_do_not_affect_result() {
+        let chunker = Chunker::Rabin {
+            min_size: 32,
+            avg_size: 128,
+            max_size: 512,
+        };
+        let content = (0..20_000u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect::<Vec<_>>();
+
+        let whole = import_bytes(content.clone(), &chunker, &mut MemoryBlockStore::default())
+            .await
+            .unwrap();
+
+        let items = content
+            .chunks(333)
+            .map(|c| Ok(c.to_vec()))
+            .collect::<Vec<_>>();
+        let split = import(
+            stream::iter(items),
+            &chunker,
+            &mut MemoryBlockStore::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(whole, split);
+    }
+}
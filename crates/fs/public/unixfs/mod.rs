@@ -0,0 +1,14 @@
+//! UnixFS import and export of public file content.
+//!
+//! Content is split into chunks that are stored as raw blocks and linked together by a balanced
+//! tree of DAG-PB nodes. This is the same layout IPFS uses, so public file content can be read
+//! from any IPFS gateway.
+
+mod chunker;
+mod exporter;
+mod importer;
+mod protobuf;
+
+pub use chunker::*;
+pub use exporter::*;
+pub use importer::*;
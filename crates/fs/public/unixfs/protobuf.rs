@@ -0,0 +1,340 @@
+//! Protobuf encoding of DAG-PB nodes and the UnixFS data they carry.
+//!
+//! Nodes are decoded with [`libipld::pb`], but encoded here canonically, with links before data
+//! and every link name written, so that the resulting CIDs match other IPFS implementations. Only
+//! the small subset of protobuf needed by the UnixFS data schema is implemented.
+
+use anyhow::Result;
+use libipld::pb::PbNode;
+
+use crate::FsError;
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+const WIRE_TYPE_VARINT: u8 = 0;
+const WIRE_TYPE_64_BIT: u8 = 1;
+const WIRE_TYPE_LENGTH_DELIMITED: u8 = 2;
+const WIRE_TYPE_32_BIT: u8 = 5;
+
+//--------------------------------------------------------------------------------------------------
+// Type Definitions
+//--------------------------------------------------------------------------------------------------
+
+/// The kind of UnixFS node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DataType {
+    Raw,
+    Directory,
+    File,
+    Metadata,
+    Symlink,
+    HamtShard,
+}
+
+/// The UnixFS data stored in the data field of a DAG-PB node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct UnixFsData {
+    pub r#type: DataType,
+    pub data: Option<Vec<u8>>,
+    pub filesize: Option<u64>,
+    pub blocksizes: Vec<u64>,
+}
+
+/// Reads protobuf fields from a byte slice.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+//--------------------------------------------------------------------------------------------------
+// Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl UnixFsData {
+    /// Creates the data of a file node.
+    pub(crate) fn file(filesize: u64, blocksizes: Vec<u64>) -> Self {
+        Self {
+            r#type: DataType::File,
+            data: None,
+            filesize: Some(filesize),
+            blocksizes,
+        }
+    }
+
+    /// Encodes the data to bytes.
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_varint_field(&mut bytes, 1, self.r#type.into());
+
+        if let Some(data) = &self.data {
+            write_bytes_field(&mut bytes, 2, data);
+        }
+
+        if let Some(filesize) = self.filesize {
+            write_varint_field(&mut bytes, 3, filesize);
+        }
+
+        for blocksize in &self.blocksizes {
+            write_varint_field(&mut bytes, 4, *blocksize);
+        }
+
+        bytes
+    }
+
+    /// Decodes the data from bytes.
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut r#type = None;
+        let mut data = None;
+        let mut filesize = None;
+        let mut blocksizes = Vec::new();
+
+        let mut reader = Reader { bytes };
+        while let Some((field, wire_type)) = reader.read_key()? {
+            match (field, wire_type) {
+                (1, WIRE_TYPE_VARINT) => r#type = Some(DataType::try_from(reader.read_varint()?)?),
+                (2, WIRE_TYPE_LENGTH_DELIMITED) => data = Some(reader.read_bytes()?.to_vec()),
+                (3, WIRE_TYPE_VARINT) => filesize = Some(reader.read_varint()?),
+                (4, WIRE_TYPE_VARINT) => blocksizes.push(reader.read_varint()?),
+                (4, WIRE_TYPE_LENGTH_DELIMITED) => {
+                    let mut packed = Reader {
+                        bytes: reader.read_bytes()?,
+                    };
+
+                    while !packed.bytes.is_empty() {
+                        blocksizes.push(packed.read_varint()?);
+                    }
+                }
+                _ => reader.skip(wire_type)?,
+            }
+        }
+
+        Ok(Self {
+            r#type: r#type.ok_or_else(|| invalid_data("Missing data type"))?,
+            data,
+            filesize,
+            blocksizes,
+        })
+    }
+}
+
+impl From<DataType> for u64 {
+    fn from(r#type: DataType) -> Self {
+        match r#type {
+            DataType::Raw => 0,
+            DataType::Directory => 1,
+            DataType::File => 2,
+            DataType::Metadata => 3,
+            DataType::Symlink => 4,
+            DataType::HamtShard => 5,
+        }
+    }
+}
+
+impl TryFrom<u64> for DataType {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u64) -> Result<Self> {
+        Ok(match value {
+            0 => DataType::Raw,
+            1 => DataType::Directory,
+            2 => DataType::File,
+            3 => DataType::Metadata,
+            4 => DataType::Symlink,
+            5 => DataType::HamtShard,
+            other => return Err(invalid_data(format!("Unknown data type {other}")).into()),
+        })
+    }
+}
+
+impl<'a> Reader<'a> {
+    /// Reads the field number and wire type of the next field.
+    fn read_key(&mut self) -> Result<Option<(u64, u8)>> {
+        if self.bytes.is_empty() {
+            return Ok(None);
+        }
+
+        let key = self.read_varint()?;
+        Ok(Some((key >> 3, (key & 0b111) as u8)))
+    }
+
+    /// Reads an unsigned LEB128 varint.
+    fn read_varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for (index, byte) in self.bytes.iter().enumerate().take(10) {
+            value |= u64::from(byte & 0x7f) << (7 * index);
+            if byte & 0x80 == 0 {
+                self.bytes = &self.bytes[index + 1..];
+                return Ok(value);
+            }
+        }
+
+        Err(invalid_data("Invalid varint").into())
+    }
+
+    /// Reads a length-delimited field.
+    fn read_bytes(&mut self) -> Result<&'a [u8]> {
+        let len = usize::try_from(self.read_varint()?)?;
+        self.take(len)
+    }
+
+    /// Takes the given number of bytes.
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.bytes.len() {
+            return Err(invalid_data("Unexpected end of data").into());
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    /// Skips over a field with the given wire type.
+    fn skip(&mut self, wire_type: u8) -> Result<()> {
+        match wire_type {
+            WIRE_TYPE_VARINT => {
+                self.read_varint()?;
+            }
+            WIRE_TYPE_64_BIT => {
+                self.take(8)?;
+            }
+            WIRE_TYPE_LENGTH_DELIMITED => {
+                self.read_bytes()?;
+            }
+            WIRE_TYPE_32_BIT => {
+                self.take(4)?;
+            }
+            other => return Err(invalid_data(format!("Unsupported wire type {other}")).into()),
+        }
+
+        Ok(())
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Encodes a DAG-PB node canonically, with its links before its data.
+pub(crate) fn encode_dag_pb(node: &PbNode) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for link in &node.links {
+        let mut link_bytes = Vec::new();
+        write_bytes_field(&mut link_bytes, 1, &link.cid.to_bytes());
+        write_bytes_field(&mut link_bytes, 2, link.name.as_bytes());
+        write_varint_field(&mut link_bytes, 3, link.size);
+
+        write_bytes_field(&mut bytes, 2, &link_bytes);
+    }
+
+    if !node.data.is_empty() {
+        write_bytes_field(&mut bytes, 1, &node.data);
+    }
+
+    bytes
+}
+
+fn invalid_data(err: impl ToString) -> FsError {
+    FsError::InvalidUnixFsData(err.to_string())
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+
+    bytes.push(value as u8);
+}
+
+fn write_varint_field(bytes: &mut Vec<u8>, field: u64, value: u64) {
+    write_varint(bytes, field << 3 | u64::from(WIRE_TYPE_VARINT));
+    write_varint(bytes, value);
+}
+
+fn write_bytes_field(bytes: &mut Vec<u8>, field: u64, value: &[u8]) {
+    write_varint(bytes, field << 3 | u64::from(WIRE_TYPE_LENGTH_DELIMITED));
+    write_varint(bytes, value.len() as u64);
+    bytes.extend_from_slice(value);
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod protobuf_tests {
+    use libipld::{
+        pb::{PbLink, PbNode},
+        Cid,
+    };
+
+    use super::*;
+
+    #[test]
+    fn encoded_data_can_be_decoded() {
+        let mut data = UnixFsData::file(1 << 40, vec![300, 1 << 40]);
+        data.data = Some(b"data".to_vec());
+
+        assert_eq!(UnixFsData::decode(&data.encode()).unwrap(), data);
+    }
+
+    #[test]
+    fn packed_block_sizes_can_be_decoded() {
+        // Type file, followed by block sizes 300 and 1 packed into a single field.
+        let bytes = [0x08, 0x02, 0x22, 0x03, 0xac, 0x02, 0x01];
+
+        assert_eq!(
+            UnixFsData::decode(&bytes).unwrap(),
+            UnixFsData {
+                r#type: DataType::File,
+                data: None,
+                filesize: None,
+                blocksizes: vec![300, 1],
+            }
+        );
+    }
+
+    #[test]
+    fn data_can_be_carried_by_dag_pb_nodes() {
+        let node = PbNode {
+            links: vec![PbLink {
+                cid: Cid::default(),
+                name: String::new(),
+                size: 12,
+            }],
+            data: UnixFsData::file(12, vec![12]).encode().into_boxed_slice(),
+        };
+
+        let decoded = PbNode::from_bytes(&encode_dag_pb(&node)).unwrap();
+
+        assert_eq!(decoded.links[0].cid, node.links[0].cid);
+        assert_eq!(decoded.links[0].size, 12);
+        assert_eq!(
+            UnixFsData::decode(&decoded.data).unwrap(),
+            UnixFsData::file(12, vec![12])
+        );
+    }
+
+    #[test]
+    fn dag_pb_nodes_are_encoded_with_links_before_data() {
+        let node = PbNode {
+            links: vec![PbLink {
+                cid: Cid::default(),
+                name: String::new(),
+                size: 12,
+            }],
+            data: b"data".to_vec().into_boxed_slice(),
+        };
+
+        let cid = Cid::default().to_bytes();
+
+        // The link, holding the hash, an empty name and the size, followed by the data.
+        let mut expected = vec![0x12, cid.len() as u8 + 6, 0x0a, cid.len() as u8];
+        expected.extend_from_slice(&cid);
+        expected.extend_from_slice(&[0x12, 0x00, 0x18, 12]);
+        expected.extend_from_slice(&[0x0a, 4, b'd', b'a', b't', b'a']);
+
+        assert_eq!(encode_dag_pb(&node), expected);
+    }
+}
@@ -4,6 +4,7 @@ use std::borrow::Cow;
 
 use anyhow::{Error, Result};
 use async_trait::async_trait;
+use futures::future;
 use js_sys::{Promise, Uint8Array};
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen_futures::JsFuture;
@@ -79,6 +80,39 @@ impl WnfsBlockStore for ForeignBlockStore {
         let bytes = Uint8Array::new(&value).to_vec();
         Ok(Cow::Owned(bytes))
     }
+
+    /// Retrieves many blocks. All calls into the host are made before any is awaited, so the
+    /// host can fetch the blocks concurrently.
+    async fn get_blocks<'a>(&'a self, cids: &[Cid]) -> Result<Vec<Cow<'a, Vec<u8>>>> {
+        let values = future::try_join_all(
+            cids.iter()
+                .map(|cid| JsFuture::from(self.0.get_block(cid.to_bytes()))),
+        )
+        .await
+        .map_err(|e| Error::msg(format!("Cannot get block: {:?}", e)))?;
+
+        Ok(values
+            .iter()
+            .map(|value| Cow::Owned(Uint8Array::new(value).to_vec()))
+            .collect())
+    }
+
+    /// Stores many arrays of bytes. All calls into the host are made before any is awaited, so
+    /// the host can store the blocks concurrently.
+    async fn put_blocks(&mut self, blocks: Vec<(Vec<u8>, IpldCodec)>) -> Result<Vec<Cid>> {
+        let values = future::try_join_all(
+            blocks
+                .into_iter()
+                .map(|(bytes, codec)| JsFuture::from(self.0.put_block(bytes, codec.into()))),
+        )
+        .await
+        .map_err(|e| Error::msg(format!("Cannot put block: {:?}", e)))?;
+
+        values
+            .iter()
+            .map(|value| Ok(Cid::try_from(&Uint8Array::new(value).to_vec()[..])?))
+            .collect()
+    }
 }
 
 impl From<IpldCodec> for Code {
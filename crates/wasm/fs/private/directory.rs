@@ -130,6 +130,40 @@ impl PrivateDirectory {
         }))
     }
 
+    /// Reads the part of the specified file's content that falls within the given byte range.
+    #[wasm_bindgen(js_name = "readRange")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn read_range(
+        &self,
+        path_segments: &Array,
+        search_latest: bool,
+        offset: usize,
+        len: usize,
+        hamt: PrivateForest,
+        store: BlockStore,
+    ) -> JsResult<Promise> {
+        let directory = Rc::clone(&self.0);
+        let store = ForeignBlockStore(store);
+        let path_segments = utils::convert_path_segments(path_segments)?;
+
+        Ok(future_to_promise(async move {
+            let WnfsPrivateOpResult {
+                root_dir,
+                hamt,
+                result,
+            } = directory
+                .read_range(&path_segments, search_latest, offset, len, hamt.0, &store)
+                .await
+                .map_err(error("Cannot read from directory"))?;
+
+            Ok(utils::create_private_op_result(
+                root_dir,
+                hamt,
+                Uint8Array::from(&result[..]),
+            )?)
+        }))
+    }
+
     /// Returns names and metadata of the direct children of a directory.
     pub fn ls(
         &self,
@@ -1,11 +1,15 @@
 //! The bindgen API for PrivateFile.
 
 use chrono::{DateTime, Utc};
-use js_sys::Date;
-use wasm_bindgen::prelude::wasm_bindgen;
+use js_sys::{Date, Object, Promise, Reflect};
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use wasm_bindgen_futures::future_to_promise;
 use wnfs::{Id, PrivateFile as WnfsPrivateFile};
 
-use crate::fs::{JsResult, Namefilter, Rng};
+use crate::{
+    fs::{utils::error, BlockStore, ForeignBlockStore, JsResult, Namefilter, PrivateForest, Rng},
+    value,
+};
 
 //--------------------------------------------------------------------------------------------------
 // Type Definitions
@@ -21,24 +25,51 @@ pub struct PrivateFile(WnfsPrivateFile);
 
 #[wasm_bindgen]
 impl PrivateFile {
-    /// Creates a new private file.
+    /// Creates an empty private file.
     #[wasm_bindgen(constructor)]
-    pub fn new(
-        parent_bare_name: Namefilter,
-        time: &Date,
-        content: Vec<u8>,
-        mut rng: Rng,
-    ) -> JsResult<PrivateFile> {
+    pub fn new(parent_bare_name: Namefilter, time: &Date, mut rng: Rng) -> JsResult<PrivateFile> {
         let time = DateTime::<Utc>::from(time);
 
         Ok(PrivateFile(WnfsPrivateFile::new(
             parent_bare_name.0,
             time,
-            content,
             &mut rng,
         )))
     }
 
+    /// Creates a new private file with content.
+    #[wasm_bindgen(js_name = "withContent")]
+    pub fn with_content(
+        parent_bare_name: Namefilter,
+        time: &Date,
+        content: Vec<u8>,
+        hamt: PrivateForest,
+        store: BlockStore,
+        mut rng: Rng,
+    ) -> JsResult<Promise> {
+        let mut store = ForeignBlockStore(store);
+        let time = DateTime::<Utc>::from(time);
+
+        Ok(future_to_promise(async move {
+            let (file, hamt) = WnfsPrivateFile::with_content(
+                parent_bare_name.0,
+                time,
+                content,
+                hamt.0,
+                &mut store,
+                &mut rng,
+            )
+            .await
+            .map_err(error("Cannot create a file with provided content"))?;
+
+            let result = Object::new();
+            Reflect::set(&result, &value!("file"), &PrivateFile(file).into())?;
+            Reflect::set(&result, &value!("hamt"), &PrivateForest(hamt).into())?;
+
+            Ok(value!(result))
+        }))
+    }
+
     /// Gets a unique id for node.
     #[wasm_bindgen(js_name = "getId")]
     pub fn get_id(&self) -> String {
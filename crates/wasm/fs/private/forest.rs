@@ -1,8 +1,15 @@
 use std::rc::Rc;
 
-use wasm_bindgen::prelude::wasm_bindgen;
+use js_sys::Promise;
+use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use wasm_bindgen_futures::future_to_promise;
 use wnfs::private::PrivateForest as WnfsPrivateForest;
 
+use crate::{
+    fs::{utils::error, BlockStore, ForeignBlockStore, JsResult},
+    value,
+};
+
 //--------------------------------------------------------------------------------------------------
 // Type Definitions
 //--------------------------------------------------------------------------------------------------
@@ -23,4 +30,20 @@ impl PrivateForest {
     pub fn new() -> PrivateForest {
         Self(Rc::new(WnfsPrivateForest::default()))
     }
+
+    /// Merges two forests that diverged from the same base.
+    pub fn merge(&self, other: &PrivateForest, store: BlockStore) -> JsResult<Promise> {
+        let forest = Rc::clone(&self.0);
+        let other = Rc::clone(&other.0);
+        let mut store = ForeignBlockStore(store);
+
+        Ok(future_to_promise(async move {
+            let merged = forest
+                .merge(&other, &mut store)
+                .await
+                .map_err(error("Cannot merge forests"))?;
+
+            Ok(value!(PrivateForest(merged)))
+        }))
+    }
 }
@@ -7,8 +7,9 @@ use js_sys::{Array, Date, Promise, Uint8Array};
 use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
 use wasm_bindgen_futures::future_to_promise;
 use wnfs::{
-    ipld::Cid, BlockStore as WnfsBlockStore, Id, PublicDirectory as WnfsPublicDirectory,
-    PublicNode as WnfsPublicNode, PublicOpResult as WnfsPublicOpResult,
+    ipld::Cid, public::unixfs::Chunker, BlockStore as WnfsBlockStore, Id,
+    PublicDirectory as WnfsPublicDirectory, PublicNode as WnfsPublicNode,
+    PublicOpResult as WnfsPublicOpResult,
 };
 
 use crate::{
@@ -128,6 +129,31 @@ impl PublicDirectory {
         }))
     }
 
+    /// Reads the part of the specified file's content that falls within the given byte range.
+    #[wasm_bindgen(js_name = "readRange")]
+    pub fn read_range(
+        &self,
+        path_segments: &Array,
+        offset: usize,
+        len: usize,
+        store: BlockStore,
+    ) -> JsResult<Promise> {
+        let directory = Rc::clone(&self.0);
+        let mut store = ForeignBlockStore(store);
+        let path_segments = utils::convert_path_segments(path_segments)?;
+
+        Ok(future_to_promise(async move {
+            let WnfsPublicOpResult { root_dir, result } = directory
+                .read_range(&path_segments, offset, len, &mut store)
+                .await
+                .map_err(error("Cannot read from directory"))?;
+
+            let result = Uint8Array::from(&result[..]);
+
+            Ok(utils::create_public_op_result(root_dir, result)?)
+        }))
+    }
+
     /// Returns names and metadata of the direct children of a directory.
     pub fn ls(&self, path_segments: &Array, store: BlockStore) -> JsResult<Promise> {
         let directory = Rc::clone(&self.0);
@@ -193,6 +219,55 @@ impl PublicDirectory {
         }))
     }
 
+    /// Imports the content as a UnixFS file and writes the file to the directory.
+    #[wasm_bindgen(js_name = "writeContent")]
+    pub fn write_content(
+        &self,
+        path_segments: &Array,
+        content: Vec<u8>,
+        time: &Date,
+        store: BlockStore,
+    ) -> JsResult<Promise> {
+        let directory = Rc::clone(&self.0);
+        let mut store = ForeignBlockStore(store);
+        let time = DateTime::<Utc>::from(time);
+        let path_segments = utils::convert_path_segments(path_segments)?;
+
+        Ok(future_to_promise(async move {
+            let WnfsPublicOpResult { root_dir, .. } = directory
+                .write_content(
+                    &path_segments,
+                    content,
+                    &Chunker::default(),
+                    time,
+                    &mut store,
+                )
+                .await
+                .map_err(error("Cannot write to directory"))?;
+
+            Ok(utils::create_public_op_result(root_dir, JsValue::NULL)?)
+        }))
+    }
+
+    /// Reads the entire content of the specified file.
+    #[wasm_bindgen(js_name = "readContent")]
+    pub fn read_content(&self, path_segments: &Array, store: BlockStore) -> JsResult<Promise> {
+        let directory = Rc::clone(&self.0);
+        let mut store = ForeignBlockStore(store);
+        let path_segments = utils::convert_path_segments(path_segments)?;
+
+        Ok(future_to_promise(async move {
+            let WnfsPublicOpResult { root_dir, result } = directory
+                .read_content(&path_segments, &mut store)
+                .await
+                .map_err(error("Cannot read from directory"))?;
+
+            let result = Uint8Array::from(&result[..]);
+
+            Ok(utils::create_public_op_result(root_dir, result)?)
+        }))
+    }
+
     /// Moves a specified path to a new location.
     #[wasm_bindgen(js_name = "basicMv")]
     pub fn basic_mv(
@@ -258,6 +333,28 @@ impl PublicDirectory {
         }))
     }
 
+    /// Merges two directories that were changed concurrently and returns the conflicts that need
+    /// to be resolved.
+    pub fn merge(&self, theirs: &PublicDirectory, store: BlockStore) -> JsResult<Promise> {
+        let directory = Rc::clone(&self.0);
+        let theirs = Rc::clone(&theirs.0);
+        let mut store = ForeignBlockStore(store);
+
+        Ok(future_to_promise(async move {
+            let WnfsPublicOpResult { root_dir, result } = directory
+                .merge(theirs, &mut store)
+                .await
+                .map_err(error("Cannot merge directories"))?;
+
+            let result = result
+                .iter()
+                .map(utils::create_merge_conflict)
+                .collect::<JsResult<Array>>()?;
+
+            Ok(utils::create_public_op_result(root_dir, result)?)
+        }))
+    }
+
     /// Gets the previous CID(s) of the directory.
     /// This will usually be an array of a single CID, but may be
     /// - an empty array, if this is the first revision of a directory
@@ -1,12 +1,13 @@
 use std::{fmt::Debug, rc::Rc};
 
 use crate::{fs::JsResult, value};
-use js_sys::{Array, Error, Object, Reflect};
+use js_sys::{Array, Error, Object, Reflect, Uint8Array};
 use wasm_bindgen::JsValue;
 use wnfs::{
+    ipld::Cid,
     private::{PrivateDirectory as WnfsPrivateDirectory, PrivateForest as WnfsPrivateForest},
     public::PublicDirectory as WnfsPublicDirectory,
-    Metadata,
+    MergeConflict, Metadata,
 };
 
 use super::{metadata::JsMetadata, PrivateDirectory, PrivateForest, PublicDirectory};
@@ -86,3 +87,23 @@ pub(crate) fn create_ls_entry(name: &String, metadata: &Metadata) -> JsResult<Js
 
     Ok(value!(entry))
 }
+
+pub(crate) fn create_merge_conflict(conflict: &MergeConflict) -> JsResult<JsValue> {
+    let entry = Object::new();
+    let path = conflict
+        .path
+        .iter()
+        .map(|name| value!(name))
+        .collect::<Array>();
+    let cid_value = |cid: &Option<Cid>| match cid {
+        Some(cid) => value!(Uint8Array::from(&cid.to_bytes()[..])),
+        None => JsValue::NULL,
+    };
+
+    Reflect::set(&entry, &value!("path"), &value!(path))?;
+    Reflect::set(&entry, &value!("base"), &cid_value(&conflict.base))?;
+    Reflect::set(&entry, &value!("ours"), &cid_value(&conflict.ours))?;
+    Reflect::set(&entry, &value!("theirs"), &cid_value(&conflict.theirs))?;
+
+    Ok(value!(entry))
+}